@@ -0,0 +1,26 @@
+#![no_main]
+//feeds each input to the screen as a binary drawing frame, drawing into a single open window
+use std::cell::RefCell;
+use libfuzzer_sys::fuzz_target;
+use rust_screen::headless::Headless;
+use rust_screen::binary::parse_binary;
+
+const OPEN_WINDOW:&str = r#"{"type":"MAKE_window_list_name","windows":{"w0":{"id":"w0","x":0,"y":0,"width":64,"height":64,"owner":"app","window_type":"plain"}}}"#;
+
+thread_local! {
+    static HEADLESS: RefCell<Headless> = RefCell::new(Headless::new().expect("could not start the headless renderer"));
+}
+
+fuzz_target!(|data: &[u8]| {
+    HEADLESS.with(|headless| {
+        let mut headless = headless.borrow_mut();
+        headless.reset();
+        headless.handle_text(OPEN_WINDOW);
+        if let Ok(msg) = parse_binary(data) {
+            headless.apply(vec![msg]);
+        }
+        if let Err(e) = headless.check() {
+            panic!("{}", e);
+        }
+    });
+});
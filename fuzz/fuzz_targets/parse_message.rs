@@ -0,0 +1,24 @@
+#![no_main]
+//feeds each input to the screen as a text message from the server, then renders the result.
+//every input is split on newlines, so one input can open windows and then draw into them.
+use std::cell::RefCell;
+use libfuzzer_sys::fuzz_target;
+use rust_screen::headless::Headless;
+
+thread_local! {
+    static HEADLESS: RefCell<Headless> = RefCell::new(Headless::new().expect("could not start the headless renderer"));
+}
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    HEADLESS.with(|headless| {
+        let mut headless = headless.borrow_mut();
+        headless.reset();
+        for line in text.lines() {
+            headless.handle_text(line);
+            if let Err(e) = headless.check() {
+                panic!("{}", e);
+            }
+        }
+    });
+});
@@ -1,10 +1,33 @@
 use crate::window::Window;
 use std::collections::HashMap;
-use crate::messages::RenderMessage;
-use std::sync::mpsc::{Receiver, Sender};
-use websocket::OwnedMessage;
+use std::sync::Arc;
+use crate::messages::{RenderMessage, ServerCapabilities};
+use crate::heartbeat::Heartbeat;
+use crate::sdl2backend::LoopExit;
+use std::sync::mpsc::Receiver;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+
+//the render loop's end of one server connection. windows remember the index of their link.
+pub struct ServerLink {
+    //shown in title bars when there is more than one server
+    pub label:String,
+    pub input:Receiver<RenderMessage>,
+    pub output:UnboundedSender<Message>,
+    pub heartbeat:Arc<Heartbeat>,
+    //what the server told us it supports. None until the server replies, or if it is too old to reply
+    pub caps:Option<ServerCapabilities>,
+    //false once the connection has dropped
+    pub open:bool,
+}
+
+impl ServerLink {
+    pub fn new(label:String, input:Receiver<RenderMessage>, output:UnboundedSender<Message>, heartbeat:Arc<Heartbeat>) -> ServerLink {
+        ServerLink { label, input, output, heartbeat, caps: None, open: true }
+    }
+}
 
 pub trait Backend {
-    fn start_loop(&mut self, windows: &mut HashMap<String, Window>, incoming: &Receiver<RenderMessage>, outgoing:&Sender<OwnedMessage>) -> Result<(),String>;
+    fn start_loop(&mut self, windows: &mut HashMap<String, Window>) -> Result<LoopExit,String>;
 
-    }
\ No newline at end of file
+    }
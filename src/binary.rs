@@ -0,0 +1,153 @@
+use std::convert::TryInto;
+use idealos_schemas::graphics::{DrawPixel, DrawPixel_name, DrawRect, DrawRect_name, DrawImage, DrawImage_name};
+use crate::messages::{RenderMessage, group_message, any_graphics_message};
+
+//compact binary framing for the high volume drawing messages. the client advertises
+//`binary_protocol` in ScreenStart. servers which don't know about it keep sending json.
+//
+//each websocket binary message is one frame. all numbers are little endian.
+//  frame  := opcode:u8 body
+//  string := length:u16 utf8 bytes
+//  color  := r:u8 g:u8 b:u8 a:u8
+//
+//  0x01 draw pixel  window:string x:i32 y:i32 color
+//  0x02 draw rect   window:string x:i32 y:i32 width:i32 height:i32 color
+//  0x03 draw image  window:string x:i32 y:i32 width:i32 height:i32 pixels:(width*height*4 bytes RGBA)
+//  0x04 group       count:u16 then count frames of type 0x01-0x03
+pub const BINARY_PROTOCOL_VERSION:i64 = 1;
+
+const OP_DRAW_PIXEL:u8 = 0x01;
+const OP_DRAW_RECT:u8 = 0x02;
+const OP_DRAW_IMAGE:u8 = 0x03;
+const OP_GROUP:u8 = 0x04;
+
+struct FrameReader<'b> {
+    data:&'b [u8],
+    pos:usize,
+}
+
+impl<'b> FrameReader<'b> {
+    fn bytes(&mut self, len:usize) -> Result<&'b [u8], String> {
+        if self.data.len() - self.pos < len {
+            return Err(format!("frame too short, wanted {} bytes at {}", len, self.pos));
+        }
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|e|e.to_string())
+    }
+    fn color(&mut self) -> Result<String, String> {
+        let c = self.bytes(4)?;
+        Ok(format!("#{:02x}{:02x}{:02x}{:02x}", c[0], c[1], c[2], c[3]))
+    }
+}
+
+pub fn parse_binary(data:&[u8]) -> Result<RenderMessage, String> {
+    let mut reader = FrameReader { data, pos: 0 };
+    let op = reader.u8()?;
+    if op == OP_GROUP {
+        let count = reader.u16()?;
+        let mut messages:Vec<any_graphics_message> = vec![];
+        for _ in 0..count {
+            let op = reader.u8()?;
+            messages.push(to_group_member(parse_frame(op, &mut reader)?)?);
+        }
+        return Ok(RenderMessage::Group(group_message {
+            type_: "group-message".to_string(),
+            category: "binary".to_string(),
+            messages
+        }));
+    }
+    parse_frame(op, &mut reader)
+}
+
+fn parse_frame(op:u8, reader:&mut FrameReader) -> Result<RenderMessage, String> {
+    match op {
+        OP_DRAW_PIXEL => Ok(RenderMessage::DrawPixel(DrawPixel {
+            type_: DrawPixel_name.to_string(),
+            window: reader.string()?,
+            x: reader.i32()? as i64,
+            y: reader.i32()? as i64,
+            color: reader.color()?,
+        })),
+        OP_DRAW_RECT => Ok(RenderMessage::FillRect(DrawRect {
+            type_: DrawRect_name.to_string(),
+            window: reader.string()?,
+            x: reader.i32()? as i64,
+            y: reader.i32()? as i64,
+            width: reader.i32()? as i64,
+            height: reader.i32()? as i64,
+            color: reader.color()?,
+        })),
+        OP_DRAW_IMAGE => {
+            let window = reader.string()?;
+            let x = reader.i32()? as i64;
+            let y = reader.i32()? as i64;
+            let width = reader.i32()?;
+            let height = reader.i32()?;
+            if width < 0 || height < 0 {
+                return Err(format!("bad image size {}x{}", width, height));
+            }
+            let len = (width as usize).checked_mul(height as usize)
+                .and_then(|n| n.checked_mul(4))
+                .ok_or("image too big")?;
+            let pixels = reader.bytes(len)?.to_vec();
+            Ok(RenderMessage::DrawImage(DrawImage {
+                type_: DrawImage_name.to_string(),
+                window,
+                color: "black".to_string(),
+                x,
+                y,
+                width: width as i64,
+                height: height as i64,
+                depth: 8,
+                channels: 4,
+                pixels
+            }))
+        }
+        _ => Err(format!("unknown binary opcode {}", op)),
+    }
+}
+
+fn to_group_member(msg:RenderMessage) -> Result<any_graphics_message, String> {
+    match msg {
+        RenderMessage::DrawPixel(m) => Ok(any_graphics_message::MAKE_DrawPixel_name {
+            window: m.window,
+            color: m.color,
+            x: m.x,
+            y: m.y
+        }),
+        RenderMessage::FillRect(m) => Ok(any_graphics_message::MAKE_DrawRect_name {
+            window: m.window,
+            color: m.color,
+            x: m.x,
+            y: m.y,
+            width: m.width,
+            height: m.height
+        }),
+        RenderMessage::DrawImage(m) => Ok(any_graphics_message::MAKE_DrawImage_name {
+            window: m.window,
+            color: m.color,
+            x: m.x,
+            y: m.y,
+            width: m.width,
+            height: m.height,
+            depth: m.depth,
+            channels: m.channels,
+            pixels: m.pixels
+        }),
+        _ => Err("groups can only contain drawing frames".to_string()),
+    }
+}
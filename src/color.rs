@@ -0,0 +1,284 @@
+use sdl2::pixels::Color;
+use crate::error::{ClientError, ClientResult};
+
+//colors in messages, themes and the config are CSS colors:
+//  names         - the CSS named colors, and transparent
+//  hex           - #rgb #rgba #rrggbb #rrggbbaa
+//  rgb() rgba()  - 0-255 or percentages, with an optional alpha from 0-1 or a percentage
+//  hsl() hsla()  - hue in degrees, saturation and lightness as percentages, optional alpha
+//the functions take the old comma form, rgb(255, 0, 0, 0.5), and the newer space form, rgb(255 0 0 / 50%).
+pub fn lookup_color(name:&str) -> ClientResult<Color> {
+    parse_color(name).ok_or_else(|| ClientError::BadColor(name.to_string()))
+}
+
+pub fn parse_color(text:&str) -> Option<Color> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(hex) = text.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    if let Some(open) = text.find('(') {
+        let args = text[open + 1..].strip_suffix(')')?;
+        return match &text[..open] {
+            "rgb" | "rgba" => parse_rgb(args),
+            "hsl" | "hsla" => parse_hsl(args),
+            _ => None,
+        };
+    }
+    named_color(&text)
+}
+
+fn parse_hex(hex:&str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    //one digit per channel is doubled, so f is ff
+    let short = |i:usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+    let long = |i:usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    match hex.len() {
+        3 => Some(Color::RGBA(short(0)?, short(1)?, short(2)?, 255)),
+        4 => Some(Color::RGBA(short(0)?, short(1)?, short(2)?, short(3)?)),
+        6 => Some(Color::RGBA(long(0)?, long(1)?, long(2)?, 255)),
+        8 => Some(Color::RGBA(long(0)?, long(1)?, long(2)?, long(3)?)),
+        _ => None,
+    }
+}
+
+//the three channels, and the alpha if there is one
+fn split_args(args:&str) -> Option<(Vec<&str>, Option<&str>)> {
+    let (channels, alpha) = if args.contains(',') {
+        let parts:Vec<&str> = args.split(',').map(|s| s.trim()).collect();
+        match parts.len() {
+            3 => (parts, None),
+            4 => (parts[..3].to_vec(), Some(parts[3])),
+            _ => return None,
+        }
+    } else {
+        let mut halves = args.splitn(2, '/');
+        let channels:Vec<&str> = halves.next()?.split_whitespace().collect();
+        let alpha = halves.next().map(|s| s.trim());
+        (channels, alpha)
+    };
+    if channels.len() != 3 { return None; }
+    Some((channels, alpha))
+}
+
+fn parse_rgb(args:&str) -> Option<Color> {
+    let (channels, alpha) = split_args(args)?;
+    let channel = |s:&str| -> Option<u8> {
+        let v = match s.strip_suffix('%') {
+            Some(pct) => number(pct)? * 255.0 / 100.0,
+            None => number(s)?,
+        };
+        Some(clamp_byte(v))
+    };
+    Some(Color::RGBA(channel(channels[0])?, channel(channels[1])?, channel(channels[2])?, parse_alpha(alpha)?))
+}
+
+fn parse_hsl(args:&str) -> Option<Color> {
+    let (channels, alpha) = split_args(args)?;
+    let hue = number(channels[0].trim_end_matches("deg"))?;
+    let saturation = number(channels[1].strip_suffix('%')?)?.max(0.0).min(100.0) / 100.0;
+    let lightness = number(channels[2].strip_suffix('%')?)?.max(0.0).min(100.0) / 100.0;
+    let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+    Some(Color::RGBA(clamp_byte(r * 255.0), clamp_byte(g * 255.0), clamp_byte(b * 255.0), parse_alpha(alpha)?))
+}
+
+fn parse_alpha(alpha:Option<&str>) -> Option<u8> {
+    match alpha {
+        None => Some(255),
+        Some(s) => {
+            let v = match s.strip_suffix('%') {
+                Some(pct) => number(pct)? / 100.0,
+                None => number(s)?,
+            };
+            Some(clamp_byte(v * 255.0))
+        }
+    }
+}
+
+fn number(s:&str) -> Option<f64> {
+    let v:f64 = s.trim().parse().ok()?;
+    if v.is_finite() { Some(v) } else { None }
+}
+
+fn clamp_byte(v:f64) -> u8 {
+    v.round().max(0.0).min(255.0) as u8
+}
+
+//the CSS Color 3 algorithm. hue in degrees, the rest 0-1
+fn hsl_to_rgb(hue:f64, saturation:f64, lightness:f64) -> (f64, f64, f64) {
+    let h = hue.rem_euclid(360.0) / 360.0;
+    let m2 = if lightness <= 0.5 {
+        lightness * (saturation + 1.0)
+    } else {
+        lightness + saturation - lightness * saturation
+    };
+    let m1 = lightness * 2.0 - m2;
+    (hue_to_rgb(m1, m2, h + 1.0 / 3.0), hue_to_rgb(m1, m2, h), hue_to_rgb(m1, m2, h - 1.0 / 3.0))
+}
+
+fn hue_to_rgb(m1:f64, m2:f64, h:f64) -> f64 {
+    let h = if h < 0.0 { h + 1.0 } else if h > 1.0 { h - 1.0 } else { h };
+    if h * 6.0 < 1.0 { return m1 + (m2 - m1) * h * 6.0; }
+    if h * 2.0 < 1.0 { return m2; }
+    if h * 3.0 < 2.0 { return m1 + (m2 - m1) * (2.0 / 3.0 - h) * 6.0; }
+    m1
+}
+
+fn named_color(name:&str) -> Option<Color> {
+    if name == "transparent" {
+        return Some(Color::RGBA(0, 0, 0, 0));
+    }
+    let rgb = match name {
+        "aliceblue" => 0xf0f8ff,
+        "antiquewhite" => 0xfaebd7,
+        "aqua" => 0x00ffff,
+        "aquamarine" => 0x7fffd4,
+        "azure" => 0xf0ffff,
+        "beige" => 0xf5f5dc,
+        "bisque" => 0xffe4c4,
+        "black" => 0x000000,
+        "blanchedalmond" => 0xffebcd,
+        "blue" => 0x0000ff,
+        "blueviolet" => 0x8a2be2,
+        "brown" => 0xa52a2a,
+        "burlywood" => 0xdeb887,
+        "cadetblue" => 0x5f9ea0,
+        "chartreuse" => 0x7fff00,
+        "chocolate" => 0xd2691e,
+        "coral" => 0xff7f50,
+        "cornflowerblue" => 0x6495ed,
+        "cornsilk" => 0xfff8dc,
+        "crimson" => 0xdc143c,
+        "cyan" => 0x00ffff,
+        "darkblue" => 0x00008b,
+        "darkcyan" => 0x008b8b,
+        "darkgoldenrod" => 0xb8860b,
+        "darkgray" => 0xa9a9a9,
+        "darkgreen" => 0x006400,
+        "darkgrey" => 0xa9a9a9,
+        "darkkhaki" => 0xbdb76b,
+        "darkmagenta" => 0x8b008b,
+        "darkolivegreen" => 0x556b2f,
+        "darkorange" => 0xff8c00,
+        "darkorchid" => 0x9932cc,
+        "darkred" => 0x8b0000,
+        "darksalmon" => 0xe9967a,
+        "darkseagreen" => 0x8fbc8f,
+        "darkslateblue" => 0x483d8b,
+        "darkslategray" => 0x2f4f4f,
+        "darkslategrey" => 0x2f4f4f,
+        "darkturquoise" => 0x00ced1,
+        "darkviolet" => 0x9400d3,
+        "deeppink" => 0xff1493,
+        "deepskyblue" => 0x00bfff,
+        "dimgray" => 0x696969,
+        "dimgrey" => 0x696969,
+        "dodgerblue" => 0x1e90ff,
+        "firebrick" => 0xb22222,
+        "floralwhite" => 0xfffaf0,
+        "forestgreen" => 0x228b22,
+        "fuchsia" => 0xff00ff,
+        "gainsboro" => 0xdcdcdc,
+        "ghostwhite" => 0xf8f8ff,
+        "gold" => 0xffd700,
+        "goldenrod" => 0xdaa520,
+        "gray" => 0x808080,
+        "green" => 0x008000,
+        "greenyellow" => 0xadff2f,
+        "grey" => 0x808080,
+        "honeydew" => 0xf0fff0,
+        "hotpink" => 0xff69b4,
+        "indianred" => 0xcd5c5c,
+        "indigo" => 0x4b0082,
+        "ivory" => 0xfffff0,
+        "khaki" => 0xf0e68c,
+        "lavender" => 0xe6e6fa,
+        "lavenderblush" => 0xfff0f5,
+        "lawngreen" => 0x7cfc00,
+        "lemonchiffon" => 0xfffacd,
+        "lightblue" => 0xadd8e6,
+        "lightcoral" => 0xf08080,
+        "lightcyan" => 0xe0ffff,
+        "lightgoldenrodyellow" => 0xfafad2,
+        "lightgray" => 0xd3d3d3,
+        "lightgreen" => 0x90ee90,
+        "lightgrey" => 0xd3d3d3,
+        "lightpink" => 0xffb6c1,
+        "lightsalmon" => 0xffa07a,
+        "lightseagreen" => 0x20b2aa,
+        "lightskyblue" => 0x87cefa,
+        "lightslategray" => 0x778899,
+        "lightslategrey" => 0x778899,
+        "lightsteelblue" => 0xb0c4de,
+        "lightyellow" => 0xffffe0,
+        "lime" => 0x00ff00,
+        "limegreen" => 0x32cd32,
+        "linen" => 0xfaf0e6,
+        "magenta" => 0xff00ff,
+        "maroon" => 0x800000,
+        "mediumaquamarine" => 0x66cdaa,
+        "mediumblue" => 0x0000cd,
+        "mediumorchid" => 0xba55d3,
+        "mediumpurple" => 0x9370db,
+        "mediumseagreen" => 0x3cb371,
+        "mediumslateblue" => 0x7b68ee,
+        "mediumspringgreen" => 0x00fa9a,
+        "mediumturquoise" => 0x48d1cc,
+        "mediumvioletred" => 0xc71585,
+        "midnightblue" => 0x191970,
+        "mintcream" => 0xf5fffa,
+        "mistyrose" => 0xffe4e1,
+        "moccasin" => 0xffe4b5,
+        "navajowhite" => 0xffdead,
+        "navy" => 0x000080,
+        "oldlace" => 0xfdf5e6,
+        "olive" => 0x808000,
+        "olivedrab" => 0x6b8e23,
+        "orange" => 0xffa500,
+        "orangered" => 0xff4500,
+        "orchid" => 0xda70d6,
+        "palegoldenrod" => 0xeee8aa,
+        "palegreen" => 0x98fb98,
+        "paleturquoise" => 0xafeeee,
+        "palevioletred" => 0xdb7093,
+        "papayawhip" => 0xffefd5,
+        "peachpuff" => 0xffdab9,
+        "peru" => 0xcd853f,
+        "pink" => 0xffc0cb,
+        "plum" => 0xdda0dd,
+        "powderblue" => 0xb0e0e6,
+        "purple" => 0x800080,
+        "rebeccapurple" => 0x663399,
+        "red" => 0xff0000,
+        "rosybrown" => 0xbc8f8f,
+        "royalblue" => 0x4169e1,
+        "saddlebrown" => 0x8b4513,
+        "salmon" => 0xfa8072,
+        "sandybrown" => 0xf4a460,
+        "seagreen" => 0x2e8b57,
+        "seashell" => 0xfff5ee,
+        "sienna" => 0xa0522d,
+        "silver" => 0xc0c0c0,
+        "skyblue" => 0x87ceeb,
+        "slateblue" => 0x6a5acd,
+        "slategray" => 0x708090,
+        "slategrey" => 0x708090,
+        "snow" => 0xfffafa,
+        "springgreen" => 0x00ff7f,
+        "steelblue" => 0x4682b4,
+        "tan" => 0xd2b48c,
+        "teal" => 0x008080,
+        "thistle" => 0xd8bfd8,
+        "tomato" => 0xff6347,
+        "turquoise" => 0x40e0d0,
+        "violet" => 0xee82ee,
+        "wheat" => 0xf5deb3,
+        "white" => 0xffffff,
+        "whitesmoke" => 0xf5f5f5,
+        "yellow" => 0xffff00,
+        "yellowgreen" => 0x9acd32,
+        _ => return None,
+    };
+    Some(Color::RGBA((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255))
+}
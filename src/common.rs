@@ -1,21 +1,22 @@
 use std::collections::HashMap;
-use websocket::OwnedMessage;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
 use crate::window::Window;
 use serde_json::{json};
 use idealos_schemas::windows::{window_refresh_request_name, window_refresh_request};
 
-pub fn send_refresh_all_windows_request(windows: &HashMap<String, Window>, sender:&Sender<OwnedMessage>) {
-    println!("sending out full refresh request");
-    for(_, win) in windows {
-        println!("sending to window {}", win.id);
+//ask the apps of one connection to redraw all of their windows
+pub fn send_refresh_all_windows_request(windows: &HashMap<String, Window>, connection: usize, sender:&UnboundedSender<Message>) {
+    debug!("sending out full refresh request");
+    for win in windows.values().filter(|win| win.connection == connection) {
+        debug!("sending to window {}", win.id);
         let msg2 = window_refresh_request {
             type_: window_refresh_request_name.to_string(),
             target:win.owner.clone(),
-            window:win.id.clone(),
+            window:win.remote_id().to_string(),
         };
         let val = json!(msg2);
-        let txt = OwnedMessage::Text(val.to_string());
+        let txt = Message::Text(val.to_string());
         sender.send(txt);
     }
 }
@@ -0,0 +1,357 @@
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use sdl2::keyboard::Keycode;
+use crate::scaling::check_scale;
+use crate::theme::{Theme, ThemeConfig};
+
+//the screen's settings, read from a TOML file. every field has a default, so a file only needs
+//the settings it changes. command line flags override the file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub servers:Vec<String>,
+    //a theme file for the window decorations, desktop and cursor. see theme.rs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme:Option<PathBuf>,
+    pub screen:ScreenConfig,
+    pub connection:ConnectionConfig,
+    pub font:FontConfig,
+    pub keys:KeyConfig,
+    pub effects:EffectsConfig,
+    pub logging:LoggingConfig,
+    //deprecated, from before themes. whatever is set here is laid over the theme
+    #[serde(skip_serializing_if = "DecorationConfig::is_unset")]
+    pub decorations:DecorationConfig,
+    #[serde(skip_serializing_if = "ColorConfig::is_unset")]
+    pub colors:ColorConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenConfig {
+    //in virtual pixels. ignored when fullscreen
+    pub width:u32,
+    pub height:u32,
+    //real pixels per virtual pixel, like 2 or 1.5. picked from the display's dpi if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale:Option<f32>,
+    pub fullscreen:bool,
+    //which display the screen goes on, counting from 0
+    pub display:u32,
+    //one screen across every display, ignoring the size, fullscreen and display settings
+    pub span:bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_cert:Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_cert:Option<PathBuf>,
+    //the token itself only comes from the command line or the environment, so it never ends up in a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_file:Option<PathBuf>,
+    //seconds
+    pub ping_interval:u64,
+    pub ping_timeout:u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    pub path:PathBuf,
+}
+
+//in virtual pixels. the top border is the title bar, as it was before themes
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DecorationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_left:Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_right:Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_top:Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_bottom:Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resize_handle:Option<i32>,
+}
+
+//the border colors cover the title bar too, as they did before themes
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background:Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border:Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_border:Option<String>,
+}
+
+impl DecorationConfig {
+    pub fn is_unset(&self) -> bool {
+        *self == DecorationConfig::default()
+    }
+    pub fn apply(&self, theme:&mut ThemeConfig) {
+        if let Some(n) = self.border_left { theme.border.left = n; }
+        if let Some(n) = self.border_right { theme.border.right = n; }
+        if let Some(n) = self.border_top { theme.title.height = n; }
+        if let Some(n) = self.border_bottom { theme.border.bottom = n; }
+        if let Some(n) = self.resize_handle { theme.border.resize_handle = n; }
+    }
+}
+
+impl ColorConfig {
+    pub fn is_unset(&self) -> bool {
+        *self == ColorConfig::default()
+    }
+    pub fn apply(&self, theme:&mut ThemeConfig) {
+        if let Some(color) = &self.background { theme.desktop.background = color.clone(); }
+        if let Some(color) = &self.border {
+            theme.border.inactive = color.clone();
+            theme.title.inactive = color.clone();
+        }
+        if let Some(color) = &self.active_border {
+            theme.border.active = color.clone();
+            theme.title.active = color.clone();
+        }
+    }
+}
+
+//SDL key names, like "Escape" or "F12"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyConfig {
+    pub quit:String,
+    pub debug_overlay:String,
+    //make the focused window more or less see-through
+    pub opacity_down:String,
+    pub opacity_up:String,
+    //minimize the focused window. a dock app brings it back
+    pub minimize:String,
+    //make the focused window fill its monitor, or put it back
+    pub maximize:String,
+}
+
+//compositor effects. their look comes from the theme. turn them off on screens too slow for them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectsConfig {
+    //beneath plain windows
+    pub shadows:bool,
+    //darken windows that don't have the focus
+    pub dim_inactive:bool,
+    //animation lengths in milliseconds, 0 for none
+    pub open_ms:u64,
+    pub close_ms:u64,
+    pub minimize_ms:u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Quiet,
+    Info,
+    Debug,
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        match s {
+            "quiet" => Ok(LogLevel::Quiet),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(format!("unknown log level {:?}, use quiet, info or debug", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level:LogLevel,
+    //print every message the render loop gets from the servers
+    pub messages:bool,
+    //start with the F12 overlay showing
+    pub debug_overlay:bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            servers: vec![],
+            theme: None,
+            screen: Default::default(),
+            connection: Default::default(),
+            font: Default::default(),
+            keys: Default::default(),
+            effects: Default::default(),
+            logging: Default::default(),
+            decorations: Default::default(),
+            colors: Default::default(),
+        }
+    }
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        ScreenConfig { width: 512, height: 320, scale: None, fullscreen: false, display: 0, span: false }
+    }
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig { ca_cert: None, pin_cert: None, token_file: None, ping_interval: 5, ping_timeout: 15 }
+    }
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        FontConfig { path: PathBuf::from("./test/font.json") }
+    }
+}
+
+impl Default for KeyConfig {
+    fn default() -> Self {
+        KeyConfig {
+            quit: "Escape".to_string(),
+            debug_overlay: "F12".to_string(),
+            opacity_down: "F7".to_string(),
+            opacity_up: "F8".to_string(),
+            minimize: "F9".to_string(),
+            maximize: "F11".to_string(),
+        }
+    }
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        EffectsConfig { shadows: true, dim_inactive: true, open_ms: 150, close_ms: 150, minimize_ms: 200 }
+    }
+}
+
+impl EffectsConfig {
+    //everything off, for --no-effects
+    pub fn none() -> Self {
+        EffectsConfig { shadows: false, dim_inactive: false, open_ms: 0, close_ms: 0, minimize_ms: 0 }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { level: LogLevel::Info, messages: false, debug_overlay: false }
+    }
+}
+
+//where the config file lives when --config isn't given: $XDG_CONFIG_HOME/idealos/screen.toml,
+//falling back to ~/.config/idealos/screen.toml
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("idealos").join("screen.toml"))
+}
+
+impl Config {
+    //a file named on the command line has to exist. the default one is optional
+    pub fn load(path:Option<&Path>) -> Result<Config, String> {
+        match path {
+            Some(path) => Config::read(path),
+            None => match default_config_path() {
+                Some(path) if path.exists() => Config::read(&path),
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+    pub fn read(path:&Path) -> Result<Config, String> {
+        let text = read_to_string(path).map_err(|e|format!("could not read config {:?}: {}", path, e))?;
+        Config::parse(&text).map_err(|e|format!("bad config {:?}: {}", path, e))
+    }
+    pub fn parse(text:&str) -> Result<Config, String> {
+        let config:Config = toml::from_str(text).map_err(|e|e.to_string())?;
+        config.check()?;
+        Ok(config)
+    }
+    //catch bad keys, scales, ping times and deprecated decorations now, rather than when the
+    //screen is already up. run again once the command line flags are applied
+    pub fn check(&self) -> Result<(), String> {
+        self.settings()?;
+        Theme::load_with(None, &self.decorations, &self.colors)?;
+        if let Some(scale) = self.screen.scale {
+            check_scale(scale)?;
+        }
+        if self.connection.ping_interval == 0 {
+            return Err("ping_interval has to be at least 1 second".to_string());
+        }
+        if self.connection.ping_timeout == 0 {
+            return Err("ping_timeout has to be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+    //the theme file, if there is one, with the deprecated [decorations] and [colors] over it
+    pub fn load_theme(&self) -> Result<Theme, String> {
+        Theme::load_with(self.theme.as_deref(), &self.decorations, &self.colors)
+    }
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e|e.to_string())
+    }
+    //the parts the render loop uses, turned into SDL types
+    pub fn settings(&self) -> Result<Settings, String> {
+        Ok(Settings {
+            quit_key: config_key(&self.keys.quit)?,
+            debug_key: config_key(&self.keys.debug_overlay)?,
+            opacity_down_key: config_key(&self.keys.opacity_down)?,
+            opacity_up_key: config_key(&self.keys.opacity_up)?,
+            minimize_key: config_key(&self.keys.minimize)?,
+            maximize_key: config_key(&self.keys.maximize)?,
+            effects: Effects {
+                shadows: self.effects.shadows,
+                dim_inactive: self.effects.dim_inactive,
+                open: Duration::from_millis(self.effects.open_ms),
+                close: Duration::from_millis(self.effects.close_ms),
+                minimize: Duration::from_millis(self.effects.minimize_ms),
+            },
+            log_messages: self.logging.messages,
+        })
+    }
+}
+
+fn config_key(name:&str) -> Result<Keycode, String> {
+    Keycode::from_name(name).ok_or_else(|| format!("unknown key {:?}", name))
+}
+
+pub struct Settings {
+    pub quit_key:Keycode,
+    pub debug_key:Keycode,
+    pub opacity_down_key:Keycode,
+    pub opacity_up_key:Keycode,
+    pub minimize_key:Keycode,
+    pub maximize_key:Keycode,
+    pub effects:Effects,
+    pub log_messages:bool,
+}
+
+//a zero duration means no animation
+pub struct Effects {
+    pub shadows:bool,
+    pub dim_inactive:bool,
+    pub open:Duration,
+    pub close:Duration,
+    pub minimize:Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        //the defaults are all valid
+        Config::default().settings().unwrap()
+    }
+}
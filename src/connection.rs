@@ -0,0 +1,257 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use serde_json::{json, Value};
+use futures_util::{SinkExt, StreamExt};
+use futures_util::future::join_all;
+use tokio::net::TcpStream;
+use tokio::runtime::Builder;
+use tokio::sync::mpsc::{UnboundedSender, UnboundedReceiver};
+use tokio::sync::oneshot;
+use tokio_tungstenite::{connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use idealos_schemas::general::{ScreenStart_name, ScreenStart};
+
+use crate::binary::BINARY_PROTOCOL_VERSION;
+use crate::heartbeat::Heartbeat;
+use crate::imagedecode::IMAGE_ENCODINGS;
+use crate::incoming::{process_incoming, supported_input_events};
+use crate::registry::MessageRegistry;
+use crate::messages::{RenderMessage, SetScreenSize, SetScreenSize_message, ScreenCapabilities, ScreenCapabilities_message, ScreenStop, ScreenStop_message, ServerCapabilities};
+use crate::outgoing::process_outgoing;
+use crate::tls::{ConnectOptions, make_request, make_tls_connector, load_pins, connect_pinned, describe_connect_error};
+
+//bumped whenever the messages exchanged with the server change, in their set or their fields.
+//3 made the scale in screen-capabilities a float
+pub const PROTOCOL_VERSION:i64 = 3;
+
+//a websocket to the server, plain or TLS
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+//what the server agreed to send, from the capabilities in its Connected reply. until that
+//arrives, or if the server is too old to send it, only plain json is accepted
+#[derive(Debug, Clone)]
+pub struct Negotiated {
+    pub binary:bool,
+    pub image_encodings:Vec<String>,
+}
+
+impl Negotiated {
+    pub fn json_only() -> Negotiated {
+        Negotiated { binary: false, image_encodings: vec!["array".to_string()] }
+    }
+    //everything this screen supports, for messages that don't come over a connection
+    pub fn everything() -> Negotiated {
+        Negotiated { binary: true, image_encodings: IMAGE_ENCODINGS.iter().map(|s|s.to_string()).collect() }
+    }
+    //what both sides support. the json array needs no agreement
+    pub fn from_caps(caps:&ServerCapabilities) -> Negotiated {
+        Negotiated {
+            binary: caps.binary_protocol == BINARY_PROTOCOL_VERSION,
+            image_encodings: IMAGE_ENCODINGS.iter()
+                .filter(|enc| **enc == "array" || caps.image_encodings.iter().any(|e| e == *enc))
+                .map(|s|s.to_string())
+                .collect(),
+        }
+    }
+    //an image, as in DrawImage, has to be in an encoding both sides agreed on.
+    //an encoding that isn't a string is left for the decoder to reject
+    pub fn check_image(&self, v:&Value) -> Result<(), String> {
+        match v.get("encoding") {
+            Some(Value::String(enc)) if !self.image_encodings.contains(enc) => {
+                Err(format!("image encoding {} wasn't agreed with the server", enc))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+//the end of one server connection that lives on the network thread
+pub struct ServerConnection {
+    pub name:String,
+    pub heartbeat:Arc<Heartbeat>,
+    //messages from the render loop to send to this server
+    pub server_out_send:UnboundedReceiver<Message>,
+    pub render_loop_send:Sender<RenderMessage>,
+}
+
+//the network thread. all of the networking, for every server, runs as tasks on one
+//event loop in this thread, so the SDL render loop keeps the main thread to itself.
+//dropping it drops every connection.
+pub struct Network {
+    thread:JoinHandle<()>,
+    done:Receiver<()>,
+    //ends the event loop, dropping whatever the connections were in the middle of
+    abort:oneshot::Sender<()>,
+    connected:Vec<Arc<AtomicBool>>,
+}
+
+impl Network {
+    //false if we never got through the websocket handshake with any server
+    pub fn was_connected(&self) -> bool {
+        self.connected.iter().any(|c| c.load(Ordering::SeqCst))
+    }
+    //tell the servers we are going away, and let the senders drain their queues up to the
+    //close message. connections that take longer than the timeout, like one still waiting
+    //on its handshake, are dropped.
+    pub fn shutdown(self, server_outs: &[UnboundedSender<Message>], timeout: Duration) {
+        let Network { thread, done, abort, .. } = self;
+        for server_out in server_outs {
+            let _ = server_out.send(Message::Text(json!(ScreenStop {
+                type_: ScreenStop_message.to_string(),
+            }).to_string()));
+            let _ = server_out.send(Message::Close(None));
+        }
+        if let Err(RecvTimeoutError::Timeout) = done.recv_timeout(timeout) {
+            info!("connections didn't finish in {:?}, dropping them", timeout);
+            let _ = abort.send(());
+            //quick, unless a task is stuck somewhere other than an await. then the
+            //thread is left behind, and dies with the process
+            if let Err(RecvTimeoutError::Timeout) = done.recv_timeout(timeout) {
+                info!("the network thread is stuck, leaving it behind");
+                return;
+            }
+        }
+        let _ = thread.join();
+        info!("connections closed");
+    }
+}
+
+//returns right away. if connecting to a server fails, or its connection drops later,
+//its render loop channel is sent RenderMessage::Disconnected with the reason.
+pub fn start_network(servers: Vec<ServerConnection>,
+                     opts: ConnectOptions,
+                     registry: Arc<MessageRegistry>,
+                     width: u32, height: u32,
+                     scale: f32,
+) -> Network {
+    let (done_send, done) = channel::<()>();
+    let (abort, aborted) = oneshot::channel::<()>();
+    let connected:Vec<Arc<AtomicBool>> = servers.iter().map(|_| Arc::new(AtomicBool::new(false))).collect();
+    let flags = connected.clone();
+    let thread = thread::spawn(move || {
+        match Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => {
+                let sessions = servers.into_iter().zip(flags.iter()).map(|(server, flag)| {
+                    run_server(server, &opts, &registry, flag, width, height, scale)
+                });
+                //dropping the sessions closes their sockets
+                runtime.block_on(async {
+                    tokio::select! {
+                        _ = join_all(sessions) => {}
+                        _ = aborted => {}
+                    }
+                });
+            }
+            Err(e) => {
+                for server in servers {
+                    let _ = server.render_loop_send.send(RenderMessage::Disconnected(format!("could not start the network loop: {}", e)));
+                }
+            }
+        }
+        let _ = done_send.send(());
+    });
+    Network {
+        thread,
+        done,
+        abort,
+        connected,
+    }
+}
+
+async fn run_server(server: ServerConnection,
+                    opts: &ConnectOptions,
+                    registry: &MessageRegistry,
+                    connected: &AtomicBool,
+                    width: u32, height: u32,
+                    scale: f32,
+) {
+    let ServerConnection { name, heartbeat, server_out_send, render_loop_send } = server;
+    let reason = run_connection(&name, opts, &heartbeat, registry, server_out_send, &render_loop_send, connected, width, height, scale).await;
+    let _ = render_loop_send.send(RenderMessage::Disconnected(reason));
+}
+
+pub async fn connect(name: &str, opts: &ConnectOptions) -> Result<WsStream, String> {
+    let request = make_request(name, opts)?;
+    let connector = if name.starts_with("wss://") {
+        let connector = make_tls_connector(opts)?;
+        if let Some(path) = &opts.pin_cert {
+            return connect_pinned(name, request, connector, &load_pins(path)?).await;
+        }
+        Some(Connector::NativeTls(connector))
+    } else {
+        None
+    };
+    let (ws, _) = connect_async_tls_with_config(request, None, connector).await
+        .map_err(|e|describe_connect_error(name, e))?;
+    Ok(ws)
+}
+
+//runs until either direction stops, and returns why
+async fn run_connection(name: &str,
+                        opts: &ConnectOptions,
+                        heartbeat: &Heartbeat,
+                        registry: &MessageRegistry,
+                        mut server_out_send: UnboundedReceiver<Message>,
+                        render_loop_send: &Sender<RenderMessage>,
+                        connected: &AtomicBool,
+                        width: u32, height: u32,
+                        scale: f32,
+) -> String {
+    info!("connecting to {}",name);
+    let ws = match connect(name, opts).await {
+        Ok(ws) => ws,
+        Err(e) => return format!("connection failed: {}", e),
+    };
+    connected.store(true, Ordering::SeqCst);
+    info!("we are connected now!");
+    let (mut server_out, server_in) = ws.split();
+
+    //the hello messages go straight out, ahead of anything the screen queued while connecting
+    for message in hello_messages(registry, opts, width, height, scale) {
+        if let Err(e) = server_out.send(message).await {
+            debug!("error sending: {:?}", e);
+            return format!("connection error: {}", e);
+        }
+    }
+
+    tokio::select! {
+        reason = process_incoming(server_in, registry, render_loop_send, heartbeat) => reason,
+        reason = process_outgoing(&mut server_out_send, &mut server_out, heartbeat) => reason,
+    }
+}
+
+fn hello_messages(registry: &MessageRegistry, opts: &ConnectOptions, width: u32, height: u32, scale: f32) -> Vec<Message> {
+    //send the initial connection message. binary_protocol tells the server it may
+    //send drawing messages as binary frames. older servers ignore it and keep using json.
+    let mut start = json!(ScreenStart{
+        type_: ScreenStart_name.to_string(),
+    });
+    start["binary_protocol"] = json!(BINARY_PROTOCOL_VERSION);
+    if let Some(token) = &opts.token {
+        start["token"] = json!(token);
+    }
+
+    let caps = json!(ScreenCapabilities{
+        type_: ScreenCapabilities_message.to_string(),
+        protocol_version: PROTOCOL_VERSION,
+        message_types: registry.message_types(),
+        image_encodings: IMAGE_ENCODINGS.iter().map(|s|s.to_string()).collect(),
+        input_events: supported_input_events(),
+        scale: scale as f64,
+        color_depth: 32,
+        client_name: env!("CARGO_PKG_NAME").to_string(),
+        client_version: env!("CARGO_PKG_VERSION").to_string(),
+    });
+
+    let size = json!(SetScreenSize{
+        type_:SetScreenSize_message.to_string(),
+        width:width as i64,
+        height:height as i64,
+    });
+
+    vec![start, caps, size].into_iter().map(|v|Message::Text(v.to_string())).collect()
+}
@@ -0,0 +1,143 @@
+use image::RgbaImage;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Texture;
+use crate::color::lookup_color;
+use crate::error::{ClientError, ClientResult};
+use crate::imagedecode::decode_draw_image;
+use crate::theme::DesktopConfig;
+use crate::validate::check_image;
+
+//tiling a tiny image over a big screen would mean a copy per tile every frame.
+//past this many tiles the tiles are drawn bigger instead
+const MAX_TILES:u32 = 4096;
+
+//errors about the wallpaper message are reported as if it were a window called this
+pub const DESKTOP:&str = "desktop";
+
+//how a wallpaper image covers the screen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WallpaperMode {
+    //stretched to the whole screen, ignoring its aspect ratio
+    Stretched,
+    //as big as fits while keeping its aspect ratio, centered, with the color around it
+    Scaled,
+    //at its own size, times the screen scale, in the middle of the screen
+    Centered,
+    //repeated from the top left, at its own size times the screen scale
+    Tiled,
+}
+
+impl Default for WallpaperMode {
+    fn default() -> Self {
+        WallpaperMode::Stretched
+    }
+}
+
+//what is drawn behind the windows. set from the theme, and by apps with desktop-set-wallpaper,
+//whichever came last
+pub struct Desktop<'a> {
+    pub color:Color,
+    pub image:Option<Texture<'a>>,
+    pub mode:WallpaperMode,
+}
+
+//the default theme's desktop, before any theme or app has set one
+impl<'a> Default for Desktop<'a> {
+    fn default() -> Self {
+        //the default background is a valid color name
+        let config = DesktopConfig::default();
+        Desktop { color: lookup_color(&config.background).unwrap(), image: None, mode: config.mode }
+    }
+}
+
+//a desktop-set-wallpaper message. fields that are left out stay as they are
+#[derive(Debug)]
+pub struct WallpaperUpdate {
+    pub color:Option<String>,
+    pub mode:Option<WallpaperMode>,
+    //Some(None) when the message has "image": null, which removes the image
+    pub image:Option<Option<RgbaImage>>,
+}
+
+//the image field takes the same encodings as DrawImage, see imagedecode.rs
+pub fn parse_wallpaper(mut v:Value) -> ClientResult<WallpaperUpdate> {
+    let color = match v.get("color") {
+        None => None,
+        Some(Value::String(color)) => Some(color.clone()),
+        Some(_) => return Err(ClientError::invalid(DESKTOP, "color must be a string".to_string())),
+    };
+    let mode = match v.get("mode") {
+        None => None,
+        Some(mode) => Some(serde_json::from_value(mode.clone())
+            .map_err(|_|ClientError::invalid(DESKTOP, format!("unknown wallpaper mode {}", mode)))?),
+    };
+    let image = match v.get_mut("image").map(Value::take) {
+        None => None,
+        Some(Value::Null) => Some(None),
+        Some(img) => {
+            let m = decode_draw_image(img).map_err(|e|ClientError::invalid(DESKTOP, e))?;
+            check_image(DESKTOP, m.width, m.height, m.pixels.len())?;
+            if m.width == 0 || m.height == 0 {
+                return Err(ClientError::invalid(DESKTOP, "wallpaper image is empty".to_string()));
+            }
+            let mut pixels = m.pixels;
+            pixels.truncate((m.width * m.height * 4) as usize);
+            let img = RgbaImage::from_raw(m.width as u32, m.height as u32, pixels)
+                .ok_or_else(|| ClientError::invalid(DESKTOP, "wallpaper pixels don't match its size".to_string()))?;
+            Some(Some(img))
+        }
+    };
+    Ok(WallpaperUpdate { color, mode, image })
+}
+
+//where to copy an image of this size so it covers a screen the way the mode says.
+//screen sizes are in real pixels, so the layout follows size and scale changes
+pub fn wallpaper_rects(mode:WallpaperMode, image:(u32, u32), screen:(u32, u32), scale:f32) -> Vec<Rect> {
+    let (iw, ih) = image;
+    let (sw, sh) = screen;
+    if iw == 0 || ih == 0 || sw == 0 || sh == 0 {
+        return vec![];
+    }
+    //a whole number of real pixels per image pixel
+    let magnify = |size:u32| ((size as f32 * scale).round() as u32).max(1);
+    match mode {
+        WallpaperMode::Stretched => vec![Rect::new(0, 0, sw, sh)],
+        WallpaperMode::Scaled => {
+            //compare the aspect ratios without floats: iw/ih against sw/sh
+            let (w, h) = if (iw as u64) * (sh as u64) > (sw as u64) * (ih as u64) {
+                (sw, ((ih as u64 * sw as u64) / iw as u64).max(1) as u32)
+            } else {
+                (((iw as u64 * sh as u64) / ih as u64).max(1) as u32, sh)
+            };
+            vec![Rect::new(((sw - w) / 2) as i32, ((sh - h) / 2) as i32, w, h)]
+        }
+        WallpaperMode::Centered => {
+            let w = magnify(iw);
+            let h = magnify(ih);
+            vec![Rect::new(((sw as i64 - w as i64) / 2) as i32, ((sh as i64 - h as i64) / 2) as i32, w, h)]
+        }
+        WallpaperMode::Tiled => {
+            let mut w = magnify(iw);
+            let mut h = magnify(ih);
+            while tiles(w, sw) * tiles(h, sh) > MAX_TILES as u64 {
+                w = w.saturating_mul(2);
+                h = h.saturating_mul(2);
+            }
+            let mut rects = vec![];
+            for row in 0..tiles(h, sh) {
+                for col in 0..tiles(w, sw) {
+                    rects.push(Rect::new((col * w as u64) as i32, (row * h as u64) as i32, w, h));
+                }
+            }
+            rects
+        }
+    }
+}
+
+fn tiles(tile:u32, screen:u32) -> u64 {
+    (screen as u64 + tile as u64 - 1) / tile as u64
+}
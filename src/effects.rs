@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+use crate::window::{Window, Bounds, Dimensions};
+
+//windows open from, and close to, this fraction of their size
+const OPEN_SCALE:f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationKind {
+    Open,
+    Close,
+    //shrinking towards the bottom of the screen, where a dock would be
+    Minimize,
+    Restore,
+}
+
+//a window changing state. it is drawn through frame() until the animation is done
+#[derive(Debug, Clone, Copy)]
+pub struct Animation {
+    pub kind:AnimationKind,
+    pub start:Instant,
+    pub duration:Duration,
+}
+
+impl Animation {
+    pub fn new(kind:AnimationKind, duration:Duration) -> Animation {
+        Animation { kind, start: Instant::now(), duration }
+    }
+    //from 0 when it starts to 1 when it is done
+    pub fn progress(&self, now:Instant) -> f32 {
+        if self.duration.as_millis() == 0 { return 1.0; }
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f32();
+        (elapsed / self.duration.as_secs_f32()).min(1.0)
+    }
+    pub fn done(&self, now:Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+    //the window as it should be drawn at this point. only the bounds and opacity change,
+    //and the bounds are in screen pixels, so the copy has a scale of 1. screen is in virtual pixels
+    pub fn frame(&self, win:&Window, now:Instant, screen:Dimensions) -> Window {
+        let t = ease(self.progress(now));
+        let (bounds, fade) = animate(self.kind, t, win.bounds(), screen);
+        let mut shown = win.clone();
+        shown.x = bounds.x;
+        shown.y = bounds.y;
+        shown.width = bounds.width;
+        shown.height = bounds.height;
+        shown.scale = 1;
+        shown.opacity = (win.opacity as f32 * fade).round() as u8;
+        shown
+    }
+}
+
+//where a window of these bounds is drawn, and how faded, at eased progress t
+pub fn animate(kind:AnimationKind, t:f32, b:Bounds, screen:Dimensions) -> (Bounds, f32) {
+    match kind {
+        AnimationKind::Open => (scale_about_center(b, OPEN_SCALE + (1.0 - OPEN_SCALE) * t), t),
+        AnimationKind::Close => (scale_about_center(b, 1.0 - (1.0 - OPEN_SCALE) * t), 1.0 - t),
+        AnimationKind::Minimize => (lerp(b, dock_spot(screen), t), 1.0 - t),
+        AnimationKind::Restore => (lerp(dock_spot(screen), b, t), t),
+    }
+}
+
+//the rings of a soft shadow below and to the right of a window's outer bounds, darkest
+//nearest the window. each ring is drawn with 1/size of the shadow color's alpha
+pub fn shadow_layers(outer:Bounds, size:i32) -> Vec<Bounds> {
+    (1..=size.max(0)).map(|i| Bounds {
+        x: outer.x + i,
+        y: outer.y + i,
+        width: outer.width,
+        height: outer.height,
+    }).collect()
+}
+
+//slow at the end, so a window settles into place
+fn ease(t:f32) -> f32 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+fn scale_about_center(b:Bounds, s:f32) -> Bounds {
+    let width = (b.width as f32 * s).round() as i32;
+    let height = (b.height as f32 * s).round() as i32;
+    Bounds { x: b.x + (b.width - width) / 2, y: b.y + (b.height - height) / 2, width, height }
+}
+
+fn dock_spot(screen:Dimensions) -> Bounds {
+    Bounds { x: screen.width / 2, y: screen.height, width: 0, height: 0 }
+}
+
+fn lerp(from:Bounds, to:Bounds, t:f32) -> Bounds {
+    let mix = |a:i32, b:i32| a + ((b - a) as f32 * t).round() as i32;
+    Bounds {
+        x: mix(from.x, to.x),
+        y: mix(from.y, to.y),
+        width: mix(from.width, to.width),
+        height: mix(from.height, to.height),
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::error::Error;
+
+//everything that can go wrong handling a message from the server. none of these
+//should take the screen down, they get logged and reported back to the app
+#[derive(Debug)]
+pub enum ClientError {
+    //the message wasn't valid json, or didn't match the schema for its type
+    Parse(serde_json::Error),
+    //a well formed message with values we can't use
+    Invalid { window:String, reason:String },
+    UnknownWindow(String),
+    BadColor(String),
+    //SDL failed to make or draw into a texture
+    Texture(String),
+    Font(String),
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+impl ClientError {
+    pub fn invalid(window:&str, reason:String) -> ClientError {
+        ClientError::Invalid { window: window.to_string(), reason }
+    }
+    //the window the error is about, if we know it, so it can be reported to the owning app
+    pub fn window(&self) -> Option<&str> {
+        match self {
+            ClientError::Invalid { window, .. } => Some(window),
+            ClientError::UnknownWindow(window) => Some(window),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Parse(e) => write!(f, "could not parse message: {}", e),
+            ClientError::Invalid { window, reason } => write!(f, "invalid message for window {}: {}", window, reason),
+            ClientError::UnknownWindow(window) => write!(f, "unknown window {}", window),
+            ClientError::BadColor(color) => write!(f, "bad color {:?}", color),
+            ClientError::Texture(e) => write!(f, "texture error: {}", e),
+            ClientError::Font(e) => write!(f, "font error: {}", e),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::Parse(e)
+    }
+}
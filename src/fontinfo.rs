@@ -8,8 +8,8 @@ pub struct FontInfo<'a> {
 }
 
 impl FontInfo<'_> {
-    pub fn ascent(&self) -> i64 {
-        return self.metrics.as_object().unwrap().get("ascent").unwrap().as_i64().unwrap();
+    pub fn ascent(&self) -> Option<i64> {
+        self.metrics.get("ascent")?.as_i64()
     }
 
     pub fn draw_text_at(&self, text: &str, x:i32, y:i32, color:&Color, canvas: &mut WindowCanvas, scale_i:i32) {
@@ -18,29 +18,20 @@ impl FontInfo<'_> {
         let scale_u:u32 = scale_i as u32;
 
         for ch in text.chars() {
-            let arr = self.metrics.as_object().unwrap().get("metrics").unwrap().as_array().unwrap();
-            if (ch as usize) < arr.len() {
-                let met_res = arr[ch as usize].as_object();
-                if let Some(met) = met_res {
-
-                    // println!("char is {} {:?}",ch,met);
-                    let sx: i32 = met.get("x").unwrap().as_u64().unwrap() as i32;
-                    let sy: i32 = met.get("y").unwrap().as_u64().unwrap() as i32;
-                    let sw: u32 = met.get("w").unwrap().as_u64().unwrap() as u32;
-                    let sh: u32 = met.get("h").unwrap().as_u64().unwrap() as u32;
-                    let bl: u32 = met.get("baseline").unwrap().as_u64().unwrap() as u32;
-
-                    let src = Rect::new(sx, sy, sw, sh);
-                    let dst = Rect::new(
-                        dx * scale_i,
-                        (y + (bl as i32)) * scale_i as i32,
-                        sw * scale_u,
-                        sh * scale_u);
-                    canvas.copy(&self.bitmap, src, dst);
-                    dx += sw as i32;
-                    dx += 1;
-                    continue;
-                }
+            let arr = self.metrics.get("metrics").and_then(|m|m.as_array());
+            if let Some(met) = arr.and_then(|arr|arr.get(ch as usize)).and_then(|m|glyph_metrics(m)) {
+                // println!("char is {} {:?}",ch,met);
+                let (sx, sy, sw, sh, bl) = met;
+                let src = Rect::new(sx, sy, sw, sh);
+                let dst = Rect::new(
+                    dx * scale_i,
+                    (y + (bl as i32)) * scale_i as i32,
+                    sw * scale_u,
+                    sh * scale_u);
+                canvas.copy(&self.bitmap, src, dst);
+                dx += sw as i32;
+                dx += 1;
+                continue;
             }
             let missing = Rect::new(dx*scale_i,dy*scale_i,10*scale_u,10*scale_u);
             canvas.set_draw_color(Color::BLACK);
@@ -50,3 +41,9 @@ impl FontInfo<'_> {
         }
     }
 }
+
+//x, y, w, h and baseline of a glyph, or None if the metrics file is missing any of them
+fn glyph_metrics(met: &serde_json::Value) -> Option<(i32, i32, u32, u32, u32)> {
+    let field = |name: &str| met.get(name).and_then(|v|v.as_u64());
+    Some((field("x")? as i32, field("y")? as i32, field("w")? as u32, field("h")? as u32, field("baseline")? as u32))
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::PixelFormatEnum;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+use sdl2::render::TextureCreator;
+use sdl2::video::WindowContext;
+use crate::sdl2backend::{SDL2Backend, Processed};
+use crate::backend::ServerLink;
+use crate::connection::{Network, ServerConnection, start_network};
+use crate::tls::ConnectOptions;
+use crate::config::Settings;
+use crate::theme::Theme;
+use crate::messages::RenderMessage;
+use crate::window::Window;
+use crate::heartbeat::Heartbeat;
+use crate::font::load_font2;
+use crate::incoming::parse_message;
+use crate::registry::{MessageRegistry, default_registry};
+use crate::validate::MAX_WINDOWS;
+use crate::scaling::use_nearest_filtering;
+
+//a renderer with no screen, using SDL's dummy video driver and the software renderer.
+//used by the fuzzer and the tests to push untrusted messages through the same code the real screen runs.
+pub struct Headless {
+    pub backend:SDL2Backend<'static>,
+    pub windows:HashMap<String, Window>,
+    pub registry:MessageRegistry,
+    //set when made with new(), which stands in for the server itself as connection 0
+    local:Option<LocalServer>,
+}
+
+struct LocalServer {
+    render_send:Sender<RenderMessage>,
+    sent:UnboundedReceiver<Message>,
+}
+
+impl Headless {
+    //a screen with a single fake server, fed with handle_text() and apply()
+    pub fn new() -> Result<Headless, String> {
+        let (render_send, render_receive) = channel();
+        let (output, sent) = unbounded_channel();
+        let link = ServerLink::new("headless".to_string(), render_receive, output,
+                                   Arc::new(Heartbeat::new(Duration::from_secs(5), Duration::from_secs(15))));
+        let mut headless = Headless::with_servers(vec![link])?;
+        headless.local = Some(LocalServer { render_send, sent });
+        Ok(headless)
+    }
+    //a screen connected to real servers, over the network. call pump() to process what they send
+    pub fn connect(servers:&[String]) -> Result<(Headless, Network), String> {
+        let mut headless = Headless::with_servers(vec![])?;
+        let network = headless.connect_to(servers);
+        Ok((headless, network))
+    }
+    //drop whatever servers the screen has, fake or real, for these ones. lets one screen be
+    //reused against several servers, since SDL can only be started once
+    pub fn connect_to(&mut self, servers:&[String]) -> Network {
+        let mut links = vec![];
+        let mut connections = vec![];
+        for server in servers {
+            let heartbeat = Arc::new(Heartbeat::new(Duration::from_secs(5), Duration::from_secs(15)));
+            let (output, server_out_send) = unbounded_channel();
+            let (render_loop_send, render_receive) = channel();
+            links.push(ServerLink::new(server.clone(), render_receive, output, heartbeat.clone()));
+            connections.push(ServerConnection { name: server.clone(), heartbeat, server_out_send, render_loop_send });
+        }
+        self.backend.servers = links;
+        self.local = None;
+        start_network(connections, ConnectOptions { ca_cert: None, pin_cert: None, token: None },
+                      Arc::new(default_registry()), 512, 320, 1.0)
+    }
+    //what to hand Network::shutdown, to say goodbye to each server
+    pub fn server_outs(&self) -> Vec<UnboundedSender<Message>> {
+        self.backend.servers.iter().map(|server| server.output.clone()).collect()
+    }
+    //the SDL context and texture creator are leaked so the backend can borrow them for 'static.
+    //make one per thread and reuse it with reset(), SDL can't be started from several threads.
+    fn with_servers(servers:Vec<ServerLink>) -> Result<Headless, String> {
+        sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+        use_nearest_filtering();
+        let sdl_context = Box::leak(Box::new(sdl2::init()?));
+        let video_subsystem = sdl_context.video()?;
+        let window = video_subsystem.window("headless", 512, 320)
+            .hidden()
+            .build()
+            .map_err(|e|e.to_string())?;
+        let canvas = window.into_canvas().software().build().map_err(|e|e.to_string())?;
+        let screen_size = canvas.output_size()?;
+        let creator:&'static TextureCreator<WindowContext> = Box::leak(Box::new(canvas.texture_creator()));
+        let font_info = load_font2(concat!(env!("CARGO_MANIFEST_DIR"), "/test/font.json"))
+            .map_err(|e|format!("could not load font: {}", e))?;
+        let backend = SDL2Backend {
+            sdl_context,
+            active_window: None,
+            canvas,
+            creator,
+            window_buffers: Default::default(),
+            back_buffers: Default::default(),
+            window_order: vec![],
+            dragging: false,
+            dragtarget: None,
+            resizing: false,
+            font_info,
+            scale: 1.0,
+            screen_size,
+            monitors: vec![],
+            restore_bounds: Default::default(),
+            servers,
+            show_debug: false,
+            settings: Settings::default(),
+            theme: Theme::default(),
+            theme_watcher: None,
+            desktop: Default::default(),
+            animations: Default::default(),
+            closing: vec![],
+        };
+        Ok(Headless { backend, windows: HashMap::new(), registry: default_registry(), local: None })
+    }
+    //forget every window and anything a test changed, so each fuzz input starts from a blank screen
+    pub fn reset(&mut self) {
+        self.windows.clear();
+        self.backend.window_buffers.clear();
+        self.backend.back_buffers.clear();
+        self.backend.window_order.clear();
+        self.backend.animations.clear();
+        self.backend.closing.clear();
+        self.backend.restore_bounds.clear();
+        self.backend.active_window = None;
+        self.backend.dragging = false;
+        self.backend.dragtarget = None;
+        self.backend.resizing = false;
+        self.backend.monitors.clear();
+        self.backend.theme = Theme::default();
+        self.backend.desktop = Default::default();
+        if let Ok(size) = self.backend.canvas.output_size() {
+            self.backend.screen_size = size;
+        }
+        for server in self.backend.servers.iter_mut() {
+            server.caps = None;
+            server.open = true;
+        }
+    }
+    //parse a text message exactly as if it came from the server, then render whatever it produced.
+    //parse errors are expected for junk input and are ignored.
+    pub fn handle_text(&mut self, txt:&str) {
+        self.queue_text(txt);
+        self.pump();
+    }
+    //parse a text message, but leave what it produced waiting for the next pump
+    pub fn queue_text(&mut self, txt:&str) {
+        if let Some(local) = &self.local {
+            let _ = parse_message(&self.registry, &local.render_send, txt.to_string());
+        }
+    }
+    pub fn apply(&mut self, messages:Vec<RenderMessage>) {
+        if let Some(local) = &self.local {
+            for msg in messages {
+                let _ = local.render_send.send(msg);
+            }
+        }
+        self.pump();
+    }
+    //handle everything the servers have sent so far, however many frames the real screen would
+    //take over it. returns the reason once they are all gone
+    pub fn pump(&mut self) -> Option<String> {
+        let gone = loop {
+            match self.backend.process_render_messages(&mut self.windows) {
+                Processed::Backlog => continue,
+                Processed::Drained => break None,
+                Processed::AllGone(reason) => break Some(reason),
+            }
+        };
+        //nobody is listening for replies, don't let them pile up
+        if let Some(local) = &mut self.local {
+            while local.sent.try_recv().is_ok() {}
+        }
+        gone
+    }
+    //pump until the check passes, or the timeout runs out
+    pub fn pump_until<F>(&mut self, timeout:Duration, check:F) -> bool where F: Fn(&Headless) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.pump();
+            if check(self) { return true; }
+            if Instant::now() > deadline { return false; }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+    //one frame, into the dummy driver's canvas. this is also what retires finished animations
+    pub fn draw(&mut self) {
+        self.backend.draw_windows(&mut self.windows);
+    }
+    //a frame at another time, to see animations part way through or finished without waiting
+    pub fn draw_at(&mut self, now:Instant) {
+        self.backend.draw_windows_at(&mut self.windows, now);
+    }
+    //a window's contents as RGBA bytes, row by row. this is the front buffer, what the screen shows
+    pub fn window_pixels(&mut self, key:&str) -> Option<Vec<u8>> {
+        let tex = self.backend.window_buffers.get_mut(key)?;
+        let mut pixels = Err(String::new());
+        //ABGR8888 is RGBA byte order on little endian machines
+        self.backend.canvas.with_texture_canvas(tex, |tc| {
+            pixels = tc.read_pixels(None, PixelFormatEnum::ABGR8888);
+        }).ok()?;
+        pixels.ok()
+    }
+    //a left click, in screen coordinates
+    pub fn click(&mut self, x:i32, y:i32) {
+        self.backend.process_mousedown(x, y, MouseButton::Left, &mut self.windows);
+        self.backend.process_mouseup(x, y, MouseButton::Left, &mut self.windows);
+    }
+    //the invariants that keep memory bounded however hostile the input
+    pub fn check(&self) -> Result<(), String> {
+        if self.windows.len() > MAX_WINDOWS {
+            return Err(format!("{} windows open, the limit is {}", self.windows.len(), MAX_WINDOWS));
+        }
+        if self.backend.window_buffers.len() != self.windows.len() {
+            return Err(format!("{} window buffers for {} windows", self.backend.window_buffers.len(), self.windows.len()));
+        }
+        if self.backend.window_order.len() != self.windows.len() {
+            return Err(format!("{} windows in the stacking order for {} windows", self.backend.window_order.len(), self.windows.len()));
+        }
+        if self.backend.back_buffers.keys().any(|id| !self.windows.contains_key(id)) {
+            return Err("back buffer left behind for a closed window".to_string());
+        }
+        if self.backend.animations.keys().any(|id| !self.windows.contains_key(id)) {
+            return Err("animation left behind for a closed window".to_string());
+        }
+        if self.backend.restore_bounds.keys().any(|id| !self.windows.contains_key(id)) {
+            return Err("restore bounds left behind for a closed window".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use std::convert::TryInto;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+//shared between the connection's sending task, which pings the server, its receiving task,
+//which sees the pongs, both on the network thread's event loop (see connection.rs), and the
+//render loop, which shows the latency in the debug overlay.
+pub struct Heartbeat {
+    pub interval:Duration,
+    pub timeout:Duration,
+    started:Instant,
+    state:Mutex<HeartbeatState>,
+}
+
+struct HeartbeatState {
+    last_heard:Instant,
+    latency:Option<Duration>,
+}
+
+impl Heartbeat {
+    pub fn new(interval:Duration, timeout:Duration) -> Heartbeat {
+        Heartbeat {
+            interval,
+            timeout,
+            started: Instant::now(),
+            state: Mutex::new(HeartbeatState {
+                last_heard: Instant::now(),
+                latency: None,
+            }),
+        }
+    }
+    //the ping carries the time it was sent, so we don't have to remember outstanding pings
+    pub fn ping(&self) -> Message {
+        let sent = self.started.elapsed().as_micros() as u64;
+        Message::Ping(sent.to_le_bytes().to_vec())
+    }
+    pub fn pong(&self, data:&[u8]) {
+        if let Ok(bytes) = data.try_into() {
+            let sent = Duration::from_micros(u64::from_le_bytes(bytes));
+            let mut state = self.state.lock().unwrap();
+            state.latency = self.started.elapsed().checked_sub(sent);
+            state.last_heard = Instant::now();
+        }
+    }
+    //any message from the server proves it is still there, not just pongs
+    pub fn heard(&self) {
+        self.state.lock().unwrap().last_heard = Instant::now();
+    }
+    pub fn latency(&self) -> Option<Duration> {
+        self.state.lock().unwrap().latency
+    }
+    pub fn is_dead(&self) -> bool {
+        self.state.lock().unwrap().last_heard.elapsed() > self.timeout
+    }
+}
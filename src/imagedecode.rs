@@ -0,0 +1,157 @@
+use serde_json::value::Value;
+use idealos_schemas::graphics::DrawImage;
+use image::ImageFormat;
+
+//refuse anything bigger than this, so a bad header can't make us allocate gigabytes
+const MAX_PIXELS:usize = 4096*4096;
+
+pub const IMAGE_ENCODINGS:&[&str] = &["array", "raw", "png", "qoi", "indexed"];
+
+//DrawImage normally carries pixels as a json array of numbers. apps can instead set
+//an `encoding` field and put the image in `data`:
+//  "array"   - the legacy json array in `pixels`
+//  "raw"     - base64 RGBA bytes, width*height*4 long
+//  "png"     - base64 png file
+//  "qoi"     - base64 qoi file
+//  "indexed" - base64 palette indexes, one byte per pixel, with `palette` as a flat list of RGBA bytes
+//everything is decoded into a plain 8bit RGBA DrawImage, so the render thread never sees the encoding.
+pub fn decode_draw_image(mut v:Value) -> Result<DrawImage, String> {
+    let encoding = match v.get("encoding") {
+        Some(Value::String(enc)) => enc.clone(),
+        Some(_) => return Err("image encoding must be a string".to_string()),
+        None => "array".to_string(),
+    };
+    if encoding == "array" {
+        return serde_json::from_value::<DrawImage>(v).map_err(|e|e.to_string());
+    }
+    let data = match v.get("data") {
+        Some(Value::String(data)) => base64::decode(data).map_err(|e|e.to_string())?,
+        _ => return Err(format!("image with encoding {} is missing data", encoding)),
+    };
+    let mut width = v["width"].as_i64().unwrap_or(0);
+    let mut height = v["height"].as_i64().unwrap_or(0);
+    let pixels = match encoding.as_str() {
+        "raw" => {
+            check_size(width, height)?;
+            if data.len() != (width * height * 4) as usize {
+                return Err(format!("raw image is {} bytes, expected {}", data.len(), width * height * 4));
+            }
+            data
+        }
+        "png" => {
+            let img = image::load_from_memory_with_format(&data, ImageFormat::Png)
+                .map_err(|e|e.to_string())?
+                .into_rgba8();
+            width = img.width() as i64;
+            height = img.height() as i64;
+            check_size(width, height)?;
+            img.into_raw()
+        }
+        "qoi" => {
+            let (w, h, pixels) = decode_qoi(&data)?;
+            width = w as i64;
+            height = h as i64;
+            pixels
+        }
+        "indexed" => {
+            check_size(width, height)?;
+            let palette:Vec<u8> = match v.get_mut("palette").map(Value::take) {
+                Some(pal) => serde_json::from_value(pal).map_err(|e|e.to_string())?,
+                None => return Err("indexed image is missing a palette".to_string()),
+            };
+            if data.len() != (width * height) as usize {
+                return Err(format!("indexed image has {} indexes, expected {}", data.len(), width * height));
+            }
+            let mut pixels = Vec::with_capacity(data.len() * 4);
+            for index in data {
+                let n = index as usize * 4;
+                if n + 4 > palette.len() {
+                    return Err(format!("palette index {} out of range", index));
+                }
+                pixels.extend_from_slice(&palette[n..n + 4]);
+            }
+            pixels
+        }
+        _ => return Err(format!("unknown image encoding {}", encoding)),
+    };
+    Ok(DrawImage {
+        type_: v["type"].as_str().unwrap_or("").to_string(),
+        window: v["window"].as_str().unwrap_or("").to_string(),
+        color: v["color"].as_str().unwrap_or("black").to_string(),
+        x: v["x"].as_i64().unwrap_or(0),
+        y: v["y"].as_i64().unwrap_or(0),
+        width,
+        height,
+        depth: 8,
+        channels: 4,
+        pixels
+    })
+}
+
+fn check_size(width:i64, height:i64) -> Result<(), String> {
+    if width <= 0 || height <= 0 || (width as usize).saturating_mul(height as usize) > MAX_PIXELS {
+        return Err(format!("bad image size {}x{}", width, height));
+    }
+    Ok(())
+}
+
+//decoder for the Quite OK Image format, https://qoiformat.org/qoi-specification.pdf
+//always returns RGBA pixels, even for 3 channel files
+pub fn decode_qoi(bytes:&[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    if bytes.len() < 14 || &bytes[0..4] != b"qoif" {
+        return Err("not a qoi image".to_string());
+    }
+    let width = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    let height = u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    check_size(width as i64, height as i64)?;
+    let count = width as usize * height as usize;
+
+    let mut pixels:Vec<u8> = Vec::with_capacity(count * 4);
+    let mut index = [[0u8; 4]; 64];
+    let mut px:[u8; 4] = [0, 0, 0, 255];
+    let mut run = 0;
+    let mut pos = 14;
+    let next = |pos:&mut usize| -> Result<u8, String> {
+        let b = *bytes.get(*pos).ok_or("qoi data ended early")?;
+        *pos += 1;
+        Ok(b)
+    };
+    while pixels.len() < count * 4 {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let b1 = next(&mut pos)?;
+            if b1 == 0xFE {
+                px[0] = next(&mut pos)?;
+                px[1] = next(&mut pos)?;
+                px[2] = next(&mut pos)?;
+            } else if b1 == 0xFF {
+                px[0] = next(&mut pos)?;
+                px[1] = next(&mut pos)?;
+                px[2] = next(&mut pos)?;
+                px[3] = next(&mut pos)?;
+            } else {
+                match b1 & 0xC0 {
+                    0x00 => px = index[b1 as usize],
+                    0x40 => {
+                        px[0] = px[0].wrapping_add((b1 >> 4) & 0x03).wrapping_sub(2);
+                        px[1] = px[1].wrapping_add((b1 >> 2) & 0x03).wrapping_sub(2);
+                        px[2] = px[2].wrapping_add(b1 & 0x03).wrapping_sub(2);
+                    }
+                    0x80 => {
+                        let b2 = next(&mut pos)?;
+                        let vg = (b1 & 0x3F).wrapping_sub(32);
+                        px[0] = px[0].wrapping_add(vg.wrapping_sub(8).wrapping_add((b2 >> 4) & 0x0F));
+                        px[1] = px[1].wrapping_add(vg);
+                        px[2] = px[2].wrapping_add(vg.wrapping_sub(8).wrapping_add(b2 & 0x0F));
+                    }
+                    _ => run = b1 & 0x3F,
+                }
+            }
+            let hash = (px[0] as usize * 3 + px[1] as usize * 5 + px[2] as usize * 7 + px[3] as usize * 11) % 64;
+            index[hash] = px;
+        }
+        pixels.extend_from_slice(&px);
+    }
+    Ok((width, height, pixels))
+}
@@ -1,166 +1,137 @@
 use core::option::Option::None;
 use core::result::Result::{Err, Ok};
-use serde_json::error::Result;
-use serde_json::value::Value;
-use websocket::receiver::Reader;
-use std::net::TcpStream;
+use crate::error::ClientResult;
 use std::sync::mpsc::Sender;
-use websocket::OwnedMessage;
-use crate::messages::{RenderMessage, CloseWindowScreen, window_list_message, group_message, any_graphics_message, WindowSetSizeRequest, WindowSetSizeRequest_message, WindowSetPosition_message, WindowSetPositionRequest, WindowSetPositionRequest_message};
-use idealos_schemas::windows::{WindowOpenDisplay_name, WindowOpenDisplay, create_child_window_display_name, create_child_window_display, close_child_window_display_name, close_child_window_display};
-use idealos_schemas::graphics::*;
-use idealos_schemas::general::{Connected_name};
-use crate::messages::RenderMessage::Connected;
+use futures_util::StreamExt;
+use futures_util::stream::SplitStream;
+use tokio_tungstenite::tungstenite::Message;
+use crate::heartbeat::Heartbeat;
+use crate::connection::{WsStream, Negotiated};
+use crate::messages::{RenderMessage, group_message, raw_group_message, any_graphics_message, MouseDown_name, MouseUp_name, KeyboardDown_name};
+use crate::registry::MessageRegistry;
+use crate::imagedecode::decode_draw_image;
+use crate::binary::parse_binary;
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Error;
 
 
-fn parse_message(renderloop_send:&Sender<RenderMessage>, txt:String) -> Result<()>{
-    let v: Value = serde_json::from_str(txt.as_str())?;
-    // println!("got a message: {:}",v);
-    match &v["type"] {
-        Value::String(msg_type) => {
-            if msg_type == Connected_name {
-                println!("really connected");
-                return Ok(())
-            }
-            if msg_type == WindowOpenDisplay_name {
-                let msg:WindowOpenDisplay = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::OpenWindow(msg));
-                return Ok(())
+//turn one text message into render messages, allowing everything the screen supports.
+//for messages that don't come over a connection. never panics on bad input
+pub fn parse_message(registry:&MessageRegistry, renderloop_send:&Sender<RenderMessage>, txt:String) -> ClientResult<()>{
+    if let Some(msg) = parse_text(registry, &txt, &Negotiated::everything())? {
+        renderloop_send.send(msg);
+    }
+    Ok(())
+}
+
+//one text message from a server, within what was agreed with it
+pub fn parse_text(registry:&MessageRegistry, txt:&str, agreed:&Negotiated) -> ClientResult<Option<RenderMessage>> {
+    // println!("got a message: {:}",txt);
+    registry.parse(txt, agreed)
+}
+
+//the input events the backend sends to apps
+pub fn supported_input_events() -> Vec<String> {
+    vec![MouseDown_name, MouseUp_name, KeyboardDown_name].iter().map(|s|s.to_string()).collect()
+}
+
+pub fn parse_group(raw:raw_group_message, agreed:&Negotiated) -> group_message {
+    let mut messages:Vec<any_graphics_message> = vec![];
+    for (i, sub_mess) in raw.messages.into_iter().enumerate() {
+        if sub_mess["type"] == "MAKE_DrawImage_name" && sub_mess.get("encoding").is_some() {
+            match agreed.check_image(&sub_mess).and_then(|_| decode_draw_image(sub_mess)) {
+                Ok(m) => messages.push(any_graphics_message::MAKE_DrawImage_name {
+                    window: m.window,
+                    color: m.color,
+                    x: m.x,
+                    y: m.y,
+                    width: m.width,
+                    height: m.height,
+                    depth: m.depth,
+                    channels: m.channels,
+                    pixels: m.pixels
+                }),
+                Err(e) => info!("skipping group member {} in {}: {}", i, raw.category, e),
             }
-            if msg_type == "MAKE_window_list_name" {
-                println!("Processed window list name");
-                let msg: window_list_message = serde_json::from_str(txt.as_str())?;
-                println!("the window list is {:?}",msg);
-                renderloop_send.send(RenderMessage::WindowList(msg));
-                return Ok(())
+            continue;
+        }
+        match serde_json::from_value::<any_graphics_message>(sub_mess) {
+            Ok(m) => messages.push(m),
+            Err(e) => {
+                info!("skipping group member {} in {}: {:?}", i, raw.category, e);
             }
+        }
+    }
+    group_message {
+        type_: raw.type_,
+        category: raw.category,
+        messages
+    }
+}
 
-            if msg_type == "group-message" {
-                // println!("got a group message {:?}",txt);
-                let msg: group_message = serde_json::from_str(txt.as_str())?;
-                // println!("the group message is {:?}",msg);
-                for(sub_mess) in &msg.messages {
-                    // println!("sub message is {:?}",sub_mess);
-                    match sub_mess {
-                        any_graphics_message::MAKE_DrawRect_name { window, color, x, y, width, height } => {
-                            renderloop_send.send(RenderMessage::FillRect(DrawRect{
-                                type_: "".to_string(),
-                                window: String::from(window),
-                                color: color.to_string(),
-                                x: *x,
-                                y: *y,
-                                width: *width,
-                                height: *height
-                            }));
-                        }
-                        any_graphics_message::MAKE_DrawImage_name { window, color, x, y, width, height, depth, channels, pixels } => {
-                            renderloop_send.send(RenderMessage::DrawImage(DrawImage{
-                                type_: "".to_string(),
-                                window: window.to_string(),
-                                color: color.to_string(),
-                                x: *x,
-                                y: *y,
-                                width: *width,
-                                height: *height,
-                                depth: *depth,
-                                channels: *channels,
-                                pixels: pixels.to_vec()
-                            }));
-                        }
+//returns false once the server has closed the connection.
+//the server's capabilities, when they arrive, replace what was agreed
+fn handle_message(message: Message, registry: &MessageRegistry, render_loop_send: &Sender<RenderMessage>, heartbeat: &Heartbeat, agreed: &mut Negotiated) -> bool {
+    heartbeat.heard();
+    match message {
+        Message::Pong(data) => {
+            heartbeat.pong(&data);
+        }
+        Message::Close(_) => {
+            debug!("got a close message");
+            return false;
+        }
+        // Say what we received
+        Message::Text(txt) => {
+            // println!("received message {:?}", txt);
+            let res = parse_text(registry, &txt, agreed);
+            match res {
+                Ok(Some(msg)) => {
+                    if let RenderMessage::ServerCapabilities(caps) = &msg {
+                        *agreed = Negotiated::from_caps(caps);
                     }
+                    let _ = render_loop_send.send(msg);
                 }
-                return Ok(())
-            }
-
-            if msg_type.eq(WindowSetPositionRequest_message) {
-                let msg:WindowSetPositionRequest = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::WindowSetPosition(msg));
-                return Ok(())
-            }
-            if msg_type.eq(WindowSetSizeRequest_message) {
-                let msg:WindowSetSizeRequest = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::WindowSetSize(msg));
-                return Ok(())
-            }
-            if msg_type == create_child_window_display_name {
-                let msg:create_child_window_display = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::CreateChildWindow(msg));
-                return Ok(())
-            }
-            if msg_type == close_child_window_display_name {
-                let msg:close_child_window_display = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::CloseChildWindow(msg));
-                return Ok(())
-            }
-            if msg_type == DrawPixel_name {
-                let msg:DrawPixel = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::DrawPixel(msg));
-                return Ok(())
-            }
-            if msg_type == DrawRect_name {
-                let msg:DrawRect = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::FillRect(msg));
-                return Ok(())
-            }
-            if msg_type == DrawImage_name {
-                let msg:DrawImage = serde_json::from_str(txt.as_str())?;
-                renderloop_send.send(RenderMessage::DrawImage(msg));
-                return Ok(())
-            }
-            match &msg_type[..] {
-                "WINDOW_CLOSE" => {
-                    let msg:CloseWindowScreen = serde_json::from_str(txt.as_str())?;
-                    renderloop_send.send(RenderMessage::CloseWindow(msg));
-                    ()
-                },
-                _ => {
-                    println!("some other message type {}",txt)
+                Ok(None) => { }
+                Err(err) => {
+                    info!("error processing message: {}",err)
                 }
             }
         }
-        _ => {
-            println!("data that's not a message!!")
+        Message::Binary(_) if !agreed.binary => {
+            info!("ignoring a binary frame, the server never agreed to the binary protocol");
         }
+        Message::Binary(data) => {
+            match parse_binary(&data) {
+                Ok(msg) => {
+                    render_loop_send.send(msg);
+                }
+                Err(err) => {
+                    info!("error processing binary message {:?}",err)
+                }
+            }
+        }
+        //tungstenite answers pings itself
+        _ => {}
     }
-   Ok(())
+    true
 }
 
-pub fn process_incoming(receiver: &mut Reader<TcpStream>, websocket_sending_tx: &Sender<OwnedMessage>, render_loop_send: &Sender<RenderMessage>) {
-    // Receive loop
-    for message in receiver.incoming_messages() {
-        //if error, send back a close message directly
+//read from the server until it goes away. returns why it stopped
+pub async fn process_incoming(mut receiver: SplitStream<WsStream>, registry: &MessageRegistry, render_loop_send: &Sender<RenderMessage>, heartbeat: &Heartbeat) -> String {
+    let mut agreed = Negotiated::json_only();
+    while let Some(message) = receiver.next().await {
         let message = match message {
             Ok(m) => m,
             Err(e) => {
-                println!("Receive Loop: {:?}", e);
-                let _ = websocket_sending_tx.send(OwnedMessage::Close(None));
-                return;
+                debug!("Receive Loop: {:?}", e);
+                return format!("connection error: {}", e);
             }
         };
-        match message {
-            OwnedMessage::Close(_) => {
-                println!("got a close message");
-                // Got a close message, so send a close message and return
-                let _ = websocket_sending_tx.send(OwnedMessage::Close(None));
-                return;
-            }
-            // Say what we received
-            OwnedMessage::Text(txt) => {
-                // println!("received message {:?}", txt);
-                let res = parse_message(render_loop_send, txt);
-                match res {
-                    Ok(_) => { }
-                    Err(err) => {
-                        println!("error processing message {:?}",err)
-                    }
-                }
-            }
-            _ => {
-                println!("Receive Loop: {:?}", message);
-            },
+        if !handle_message(message, registry, render_loop_send, heartbeat, &mut agreed) {
+            return "server closed the connection".to_string();
         }
     }
+    "server closed the connection".to_string()
 }
@@ -0,0 +1,32 @@
+//the screen as a library, so the fuzzer and the tests can drive the message handling directly
+#[macro_use]
+pub mod logging;
+pub mod messages;
+pub mod window;
+pub mod incoming;
+pub mod outgoing;
+pub mod backend;
+pub mod sdl2backend;
+pub mod common;
+pub mod fontinfo;
+pub mod font;
+pub mod imagedecode;
+pub mod binary;
+pub mod tls;
+pub mod heartbeat;
+pub mod connection;
+pub mod error;
+pub mod validate;
+pub mod registry;
+pub mod namespace;
+#[cfg(feature = "testing")]
+pub mod mockserver;
+pub mod config;
+pub mod theme;
+pub mod desktop;
+pub mod color;
+pub mod effects;
+pub mod scaling;
+pub mod monitors;
+#[cfg(feature = "testing")]
+pub mod headless;
@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use crate::config::LogLevel;
+
+//what gets printed, from the logging section of the config. set once at startup, before the
+//render loop and network thread start. print with info! and debug! rather than println!
+static LEVEL:AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_level(level:LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level:LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+//things a person running the screen wants to know: connections coming and going, bad messages
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::config::LogLevel::Info) { println!($($arg)*); }
+    };
+}
+
+//everything else, for working on the screen itself
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::config::LogLevel::Debug) { println!($($arg)*); }
+    };
+}
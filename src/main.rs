@@ -1,18 +1,28 @@
 use std::collections::HashMap;
 use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::Arc;
+use rust_screen::heartbeat::Heartbeat;
 use std::{thread, env};
 
-use websocket::{OwnedMessage};
-use websocket::ClientBuilder;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
 
-use messages::{RenderMessage};
-use window::{Window};
+use rust_screen::messages::{RenderMessage};
+use rust_screen::window::{Window};
 
-use crate::incoming::process_incoming;
-use crate::outgoing::process_outgoing;
-use crate::sdl2backend::SDL2Backend;
-use crate::fontinfo::FontInfo;
-use std::fs::File;
+use rust_screen::connection::{start_network, ServerConnection};
+use rust_screen::backend::ServerLink;
+use rust_screen::sdl2backend::{SDL2Backend, LoopExit};
+use rust_screen::fontinfo::FontInfo;
+use std::fs::{File, read_to_string};
+use std::path::PathBuf;
+use rust_screen::tls::ConnectOptions;
+use rust_screen::config::{Config, LogLevel, EffectsConfig};
+use rust_screen::{logging, info, debug};
+use rust_screen::scaling::{scale_for_dpi, real_size, virtual_size, use_nearest_filtering};
+use rust_screen::monitors::{span, monitor_bounds};
+use rust_screen::theme::{Theme, ThemeWatcher};
+use rust_screen::registry::default_registry;
 use std::io::BufReader;
 use std::error::Error;
 use sdl2::pixels::PixelFormatEnum;
@@ -24,187 +34,279 @@ use sdl2::mouse::Cursor;
 use sdl2::surface::Surface;
 use sdl2::rect::Rect;
 use serde_json::{json};
-use idealos_schemas::general::{ScreenStart_name, ScreenStart};
-use crate::font::load_font2;
+use rust_screen::font::load_font2;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::{VideoSubsystem, video, render, version};
 use std::time::Duration;
-use crate::messages::{SetScreenSize, SetScreenSize_message};
 use structopt::StructOpt;
 
-mod messages;
-mod window;
-mod incoming;
-mod outgoing;
-mod backend;
-mod sdl2backend;
-mod common;
-mod fontinfo;
-mod font;
+
+//process exit codes
+const EXIT_STARTUP_FAILED:i32 = 1;
+const EXIT_CONNECTION_FAILED:i32 = 2;
+const EXIT_CONNECTION_LOST:i32 = 3;
+
+//how long to wait for queued messages to go out when quitting
+const SHUTDOWN_TIMEOUT:Duration = Duration::from_secs(2);
 
 #[derive(StructOpt)]
 #[structopt(name = "example", about="example rust client usage")]
 struct Cli {
-    server:String,
+    //one or more servers. windows from all of them share the screen. replaces the servers in the config file
+    servers:Vec<String>,
+    //defaults to $XDG_CONFIG_HOME/idealos/screen.toml, if it exists
+    #[structopt(long=("--config"), parse(from_os_str))]
+    config:Option<PathBuf>,
+    //a theme file for the window decorations. replaces the one in the config file
+    #[structopt(long=("--theme"), parse(from_os_str))]
+    theme:Option<PathBuf>,
+    //print the config after the command line is applied, then exit
+    #[structopt(long=("--print-config"))]
+    print_config:bool,
     #[structopt(short, long)]
     fullscreen:bool,
-    #[structopt(long=("--scale"),default_value="1")]
-    scale: u32,
-    #[structopt(long=("--width"),default_value="0")]
-    width:u32,
-    #[structopt(long=("--height"),default_value="0")]
-    height:u32,
+    //real pixels per virtual pixel, can be fractional. picked from the display's dpi if not set
+    #[structopt(long=("--scale"))]
+    scale:Option<f32>,
+    #[structopt(long=("--width"))]
+    width:Option<u32>,
+    #[structopt(long=("--height"))]
+    height:Option<u32>,
+    #[structopt(long=("--font"), parse(from_os_str))]
+    font:Option<PathBuf>,
+    //extra CA certificates (PEM) to trust for wss:// servers
+    #[structopt(long=("--ca-cert"), parse(from_os_str))]
+    ca_cert:Option<PathBuf>,
+    //the server's own certificate (PEM). wss:// servers must present exactly this one
+    #[structopt(long=("--pin-cert"), parse(from_os_str))]
+    pin_cert:Option<PathBuf>,
+    #[structopt(long=("--token"), env="IDEALOS_TOKEN", hide_env_values=true)]
+    token:Option<String>,
+    #[structopt(long=("--token-file"), parse(from_os_str))]
+    token_file:Option<PathBuf>,
+    //seconds between pings to the server
+    #[structopt(long=("--ping-interval"))]
+    ping_interval:Option<u64>,
+    //seconds without hearing from the server before the connection is considered dead
+    #[structopt(long=("--ping-timeout"))]
+    ping_timeout:Option<u64>,
+    //quiet, info or debug
+    #[structopt(long=("--log-level"))]
+    log_level:Option<LogLevel>,
+    //no shadows, dimming or animations, for slow screens
+    #[structopt(long=("--no-effects"))]
+    no_effects:bool,
 }
-pub fn main() -> Result<(),String> {
-    let args:Cli = Cli::from_args();
 
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    println!("verison is {}", sdl2::version::version());
-    println!("current driver is {:}",video_subsystem.current_video_driver());
-    let display_count = video_subsystem.num_video_displays()?;
-    println!("display count {:}",display_count);
-    for d in drivers() {
-        println!("video driver {}",d);
+impl Cli {
+    //flags given on the command line win over the config file
+    fn apply(&self, config:&mut Config) -> Result<(), String> {
+        if !self.servers.is_empty() { config.servers = self.servers.clone(); }
+        if let Some(theme) = &self.theme { config.theme = Some(theme.clone()); }
+        if self.fullscreen { config.screen.fullscreen = true; }
+        if let Some(scale) = self.scale { config.screen.scale = Some(scale); }
+        if let Some(width) = self.width { config.screen.width = width; }
+        if let Some(height) = self.height { config.screen.height = height; }
+        if let Some(font) = &self.font { config.font.path = font.clone(); }
+        if let Some(path) = &self.ca_cert { config.connection.ca_cert = Some(path.clone()); }
+        if let Some(path) = &self.pin_cert { config.connection.pin_cert = Some(path.clone()); }
+        if let Some(path) = &self.token_file { config.connection.token_file = Some(path.clone()); }
+        if let Some(secs) = self.ping_interval { config.connection.ping_interval = secs; }
+        if let Some(secs) = self.ping_timeout { config.connection.ping_timeout = secs; }
+        if let Some(level) = self.log_level { config.logging.level = level; }
+        if self.no_effects { config.effects = EffectsConfig::none(); }
+        config.check()
     }
+}
 
-    for d in render::drivers() {
-        println!("render driver {:?}",d);
+pub fn main() {
+    let args:Cli = Cli::from_args();
+    match run(args) {
+        Ok(()) => (),
+        Err((code, msg)) => {
+            eprintln!("{}", msg);
+            std::process::exit(code);
+        }
     }
+}
 
-    let display_mode = video_subsystem.current_display_mode(0).unwrap();
-    println!("Display mode is {:?}", display_mode);
+fn run(args:Cli) -> Result<(),(i32,String)> {
+    let startup = |e:String| (EXIT_STARTUP_FAILED, e);
 
-    let mut rwidth:u32 = 512;
-    let mut rheight:u32 = 320;
-    let mut vwidth:u32 = 512;
-    let mut vheight:u32 = 320;
-    if(args.width > 0) {
-        vwidth = args.width;
-        rwidth = args.width*args.scale;
+    let mut config = Config::load(args.config.as_deref()).map_err(startup)?;
+    args.apply(&mut config).map_err(startup)?;
+    if args.print_config {
+        print!("{}", config.to_toml().map_err(startup)?);
+        return Ok(());
     }
-    if(args.height > 0) {
-        vheight = args.height;
-        rheight = args.height*args.scale;
+    if config.servers.is_empty() {
+        return Err(startup("no servers to connect to. list them on the command line or in the config file".to_string()));
     }
-    if(args.fullscreen) {
-        rwidth = display_mode.w as u32;
-        rheight = display_mode.h as u32;
-        vwidth = rwidth/args.scale;
-        vheight = rheight/args.scale;
+    let settings = config.settings().map_err(startup)?;
+    logging::set_level(config.logging.level);
+
+    let sdl_context = sdl2::init().map_err(startup)?;
+    let video_subsystem = sdl_context.video().map_err(startup)?;
+    debug!("verison is {}", sdl2::version::version());
+    debug!("current driver is {:}",video_subsystem.current_video_driver());
+    if logging::enabled(LogLevel::Debug) {
+        for d in drivers() {
+            debug!("video driver {}",d);
+        }
+
+        for d in render::drivers() {
+            debug!("render driver {:?}",d);
+        }
     }
 
-    println!("virtual size {} {}", vwidth, vheight);
-    println!("real size {} {}",rwidth,rheight);
+    let display_count = video_subsystem.num_video_displays().map_err(startup)?;
+    let displays = (0..display_count).map(|n| video_subsystem.display_bounds(n))
+        .collect::<Result<Vec<_>, _>>().map_err(startup)?;
+    let display = config.screen.display as i32;
+    if display >= display_count {
+        return Err(startup(format!("there is no display {}, there are {}", display, display_count)));
+    }
+    let display_mode = video_subsystem.current_display_mode(display).map_err(startup)?;
+    debug!("display count {:}", display_count);
+    debug!("Display mode is {:?}", display_mode);
+    debug!("Display bounds are {:?}", displays);
+    let spanned = if config.screen.span { span(&displays) } else { None };
 
-    let mut inter1 = video_subsystem.window("rust-sdl2 demo: Video", rwidth, rheight);
+    //without a scale in the config, one to suit the display
+    let scale = match config.screen.scale {
+        Some(scale) => scale,
+        None => video_subsystem.display_dpi(display).map(|(ddpi, _, _)| scale_for_dpi(ddpi)).unwrap_or(1.0),
+    };
+    let (rwidth, rheight) = match spanned {
+        Some(all) => (all.width(), all.height()),
+        None if config.screen.fullscreen => (display_mode.w as u32, display_mode.h as u32),
+        None => real_size((config.screen.width, config.screen.height), scale),
+    };
 
-    let inter2 = if args.fullscreen {
-        inter1.fullscreen()
+    use_nearest_filtering();
+    let mut builder = video_subsystem.window("rust-sdl2 demo: Video", rwidth, rheight);
+    let on = displays[display as usize];
+    if let Some(all) = spanned {
+        //fullscreen only ever covers one display, so spanning is a borderless window over all of them
+        builder.position(all.x(), all.y()).borderless();
+    } else if config.screen.fullscreen {
+        //fullscreen goes on whichever display the window starts on
+        builder.position(on.x(), on.y()).fullscreen();
     } else {
-        inter1.position_centered()
-    };
-    let window = inter2
+        //the screen follows the host window's size. see SDL2Backend::set_screen_size
+        builder.position(on.x() + (on.width() as i32 - rwidth as i32) / 2, on.y() + (on.height() as i32 - rheight as i32) / 2)
+            .resizable();
+    }
+    let window = builder
             .opengl()
             .build()
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| startup(e.to_string()))?;
 
     let canvas_builder = window.into_canvas();
-    let mut canvas = canvas_builder.build().map_err(|e| e.to_string())?;
+    let mut canvas = canvas_builder.build().map_err(|e| startup(e.to_string()))?;
+    //whatever size the renderer really got, which for fullscreen is the display's.
+    //rounded up, so the edges aren't lost. the render loop keeps this up to date
+    let screen_size = canvas.output_size().map_err(startup)?;
+    let (vwidth, vheight) = virtual_size(screen_size, scale);
+    info!("virtual size {} {}", vwidth, vheight);
+    info!("real size {} {} at scale {}", screen_size.0, screen_size.1, scale);
     let creator = canvas.texture_creator();
 
     let mut windows:HashMap<String,Window> = HashMap::new();
 
+    let token = match (&args.token, &config.connection.token_file) {
+        (Some(token), _) => Some(token.clone()),
+        (None, Some(path)) => Some(read_to_string(path)
+            .map_err(|e|startup(format!("could not read token file {:?}: {}", path, e)))?
+            .trim().to_string()),
+        (None, None) => None,
+    };
+    let opts = ConnectOptions {
+        ca_cert: config.connection.ca_cert.clone(),
+        pin_cert: config.connection.pin_cert.clone(),
+        token,
+    };
+
+    //each server gets its own pair of channels. the render loop end of them is a ServerLink,
+    //the network end a ServerConnection.
+    let mut links:Vec<ServerLink> = vec![];
+    let mut connections:Vec<ServerConnection> = vec![];
+    for server in &config.servers {
+        let heartbeat = Arc::new(Heartbeat::new(Duration::from_secs(config.connection.ping_interval),
+                                                Duration::from_secs(config.connection.ping_timeout)));
+        //channel to talk to the server sender task. unbounded, so the render loop never waits on the network
+        let (server_out_receive, server_out_send) = unbounded_channel::<Message>();
+        //channel to connect server receiver task and render loop
+        let (render_loop_send, render_loop_receive) = channel::<RenderMessage>();
+        links.push(ServerLink::new(server_label(server), render_loop_receive, server_out_receive, heartbeat.clone()));
+        connections.push(ServerConnection {
+            name: server.clone(),
+            heartbeat,
+            server_out_send,
+            render_loop_send,
+        });
+    }
+    let server_outs:Vec<UnboundedSender<Message>> = links.iter().map(|link| link.output.clone()).collect();
+
     let mut backend = SDL2Backend {
         sdl_context: &sdl_context,
         active_window: None,
         canvas:canvas,
         creator: &creator,
         window_buffers: Default::default(),
+        back_buffers: Default::default(),
         window_order: vec![],
         dragging: false,
         dragtarget: None,
         resizing: false,
-        font_info: load_font2("./test/font.json").unwrap(),
-        scale:args.scale,
-        iscale: args.scale as i32,
+        font_info: load_font2(&config.font.path.to_string_lossy())
+            .map_err(|e| startup(format!("could not load font {:?}: {}", config.font.path, e)))?,
+        scale,
+        screen_size,
+        //the monitors are the displays, where they sit in the spanning window
+        monitors: match spanned {
+            Some(all) => monitor_bounds(&displays, (all.x(), all.y()), scale),
+            None => vec![],
+        },
+        restore_bounds: Default::default(),
+        servers: links,
+        show_debug: config.logging.debug_overlay,
+        settings,
+        theme: Theme::default(),
+        theme_watcher: config.theme.clone().map(|path| ThemeWatcher::new(path).with_deprecated(&config.decorations, &config.colors)),
+        desktop: Default::default(),
+        animations: Default::default(),
+        closing: vec![],
     };
+    let theme = config.load_theme().map_err(startup)?;
+    backend.set_theme(theme).map_err(|e| startup(e.to_string()))?;
 
     sdl_context.mouse().show_cursor(false);
 
-    //channel to talk to server sender thread
-    let (server_out_receive, server_out_send) = channel();
-    //channel to connect server receiver thread and render loop
-    let (render_loop_send, render_loop_receive) = channel::<RenderMessage>();
-
-    let r2 = server_out_receive.clone();
-    let rr2 = render_loop_send.clone();
-
-    let receive_loop = thread::spawn(move || {
-        start_connection(&args.server.clone(), r2, rr2, server_out_send, vwidth, vheight);
-    });
-
-    backend.start_loop(
-        &mut windows,
-        &render_loop_receive,
-        &server_out_receive.clone()
-    );
+    let registry = Arc::new(default_registry());
+    //the networking runs on its own thread, the render loop keeps this one
+    let network = start_network(connections, opts, registry, vwidth, vheight, scale);
 
-    println!("SDL thread is ending");
-    Ok(())
-}
+    let exit = backend.start_loop(&mut windows);
 
-fn start_connection(name:&str,
-                    server_out_receive: Sender<OwnedMessage>,
-                    render_loop_send: Sender<RenderMessage>,
-                    server_out_send: Receiver<OwnedMessage>,
-                    width: u32, height: u32
-) {
-    println!("connecting to {}",name);
-    let mut client = ClientBuilder::new(name)
-        .unwrap()
-        .connect_insecure()
-        .unwrap();
-
-    println!("we are connected now!");
-    //websocket connection
-    let (mut server_in, mut server_out) = client.split().unwrap();
-    //loop for receiving
-    let sor = server_out_receive.clone();
-    let receive_loop = thread::spawn(move || {
-        process_incoming(&mut server_in, &sor, &render_loop_send);
-    });
-
-    //loop for sending
-    let send_loop = thread::spawn(move || {
-        process_outgoing(&server_out_send, &mut server_out);
-    });
-
-    //send the initial connection message
-    let message = OwnedMessage::Text(json!(ScreenStart{
-        type_: ScreenStart_name.to_string(),
-    }).to_string());
-    match server_out_receive.send(message) {
-        Ok(()) => (),
-        Err(e) => {
-            println!("error sending: {:?}", e);
-        }
+    debug!("SDL thread is ending");
+    let connected = network.was_connected();
+    //the servers hear the screen is stopping even when the render loop failed
+    network.shutdown(&server_outs, SHUTDOWN_TIMEOUT);
+    match exit.map_err(startup)? {
+        LoopExit::Quit => Ok(()),
+        LoopExit::Disconnected(reason) if connected => Err((EXIT_CONNECTION_LOST, reason)),
+        LoopExit::Disconnected(reason) => Err((EXIT_CONNECTION_FAILED, reason)),
     }
+}
 
-    let msg2 = OwnedMessage::Text(json!(SetScreenSize{
-        type_:SetScreenSize_message.to_string(),
-        width:width as i64,
-        height:height as i64,
-    }).to_string());
-    match server_out_receive.send(msg2) {
-        Ok(()) => (),
-        Err(e) => {
-            println!("error sending: {:?}", e);
-        }
+//the server url without the scheme, for title bars
+fn server_label(server:&str) -> String {
+    match server.find("://") {
+        Some(n) => server[n+3..].trim_end_matches('/').to_string(),
+        None => server.to_string(),
     }
-
-    println!("Waiting for child threads to exit");
 }
 
 fn load_font<'a>(png_path: &str, json_path: &str, creator: &'a TextureCreator<WindowContext>) -> Result<FontInfo<'a>, String> {
@@ -226,7 +328,7 @@ pub fn load_json(json_path:&str) -> Result<serde_json::Value, Box<dyn Error>> {
     let file = File::open(json_path)?;
     let reader = BufReader::new(file);
     let metrics:serde_json::Value =  serde_json::from_reader(reader)?;
-    println!("metrics are object? {:?}",metrics.is_object());
+    debug!("metrics are object? {:?}",metrics.is_object());
     return Ok(metrics)
 }
 pub fn image_to_texture_with_transparent_color<'a>(rust_img:&RgbaImage, creator:&'a TextureCreator<WindowContext>) -> Result<Texture<'a>, String>{
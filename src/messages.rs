@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use idealos_schemas::windows::{WindowOpenDisplay, create_child_window_display, close_child_window_display};
 use idealos_schemas::graphics::{DrawPixel, DrawImage, DrawRect};
 use idealos_schemas::general::{Connected};
+use crate::desktop::WallpaperUpdate;
 
 
 
@@ -20,6 +21,7 @@ pub struct window_info {
 }
 pub type window_map = HashMap<String,window_info>;
 
+pub const WindowList_message: &str = "MAKE_window_list_name";
 #[derive(Serialize, Deserialize, Debug)]
 pub struct window_list_message {
     #[serde(rename = "type")]
@@ -28,6 +30,7 @@ pub struct window_list_message {
 }
 
 
+pub const SetFocusedWindow_name: &str = "MAKE_SetFocusedWindow_name";
 #[derive(Serialize, Deserialize, Debug)]
 pub struct set_focused_window_message {
     #[serde(rename = "type")]
@@ -63,6 +66,12 @@ pub struct drawimage_message {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum any_graphics_message {
+    MAKE_DrawPixel_name {
+        window:String,
+        color:String,
+        x:i64,
+        y:i64,
+    },
     MAKE_DrawRect_name {
         window:String,
         color:String,
@@ -82,8 +91,42 @@ pub enum any_graphics_message {
         channels:i64,
         pixels:Vec<u8>,
     },
+    #[serde(rename = "window-copy-rect")]
+    CopyRect {
+        window:String,
+        x:i64,
+        y:i64,
+        width:i64,
+        height:i64,
+        dx:i64,
+        dy:i64,
+    },
+    #[serde(rename = "window-commit")]
+    Commit {
+        window:String,
+    },
+    //translate all following members by x,y until the matching pop
+    #[serde(rename = "group-push-translate")]
+    PushTranslate {
+        x:i64,
+        y:i64,
+    },
+    #[serde(rename = "group-pop-translate")]
+    PopTranslate,
+    //clip all following members to this rect, in the current translated coordinates.
+    //nested clips are intersected with their parent
+    #[serde(rename = "group-push-clip")]
+    PushClip {
+        x:i64,
+        y:i64,
+        width:i64,
+        height:i64,
+    },
+    #[serde(rename = "group-pop-clip")]
+    PopClip,
 }
 
+pub const Group_message: &str = "group-message";
 #[derive(Serialize, Deserialize, Debug)]
 pub struct group_message {
     #[serde(rename = "type")]
@@ -92,6 +135,17 @@ pub struct group_message {
     pub messages:Vec<any_graphics_message>,
 }
 
+//the group as it comes over the wire. members are parsed one at a time so that
+//a single unknown or malformed member doesn't take down the whole group
+#[derive(Serialize, Deserialize, Debug)]
+pub struct raw_group_message {
+    #[serde(rename = "type")]
+    pub type_:String,
+    #[serde(default)]
+    pub category:String,
+    pub messages:Vec<serde_json::Value>,
+}
+
 
 #[derive(Debug)]
 pub enum RenderMessage {
@@ -106,6 +160,17 @@ pub enum RenderMessage {
     DrawPixel(DrawPixel),
     DrawImage(DrawImage),
     FillRect(DrawRect),
+    CopyRect(WindowCopyRect),
+    Group(group_message),
+    SetDoubleBuffered(WindowSetDoubleBuffered),
+    Commit(WindowCommit),
+    ServerCapabilities(ServerCapabilities),
+    SetWallpaper(WallpaperUpdate),
+    SetOpacity(WindowSetOpacity),
+    SetMinimized(WindowSetMinimized),
+    SetScale(WindowSetScale),
+    //the connection to the server is gone, or never came up
+    Disconnected(String),
 }
 
 
@@ -186,6 +251,7 @@ pub struct OpenWindowScreen {
     pub window: WindowInfo,
 }
 
+pub const CloseWindow_message: &str = "WINDOW_CLOSE";
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CloseWindowScreen {
     #[serde(rename = "type")]
@@ -293,6 +359,52 @@ pub struct WindowSetSizeRequest {
     pub height:i64,
 }
 
+//sent to the app that owns a window when the close button in its title bar is clicked.
+//the app decides whether to actually close it
+pub const WindowCloseRequest_message: &str = "window-close-request";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowCloseRequest {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub target:String,
+    pub window:String,
+}
+
+//sets the desktop background color, image and how the image is laid out. parsed by desktop::parse_wallpaper
+pub const DesktopSetWallpaper_message: &str = "desktop-set-wallpaper";
+
+//how opaque a window is drawn, from 0 (invisible) to 1, for translucent panels and the like
+pub const WindowSetOpacity_message: &str = "window-set-opacity";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowSetOpacity {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+    pub opacity:f64,
+}
+
+//hides a window, or brings it back. sent by apps such as a dock, and by the screen
+//to the window's app when the user minimizes it
+pub const WindowSetMinimized_message: &str = "window-set-minimized";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowSetMinimized {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+    pub minimized:bool,
+}
+
+//magnifies a window's contents by a whole number, for small pixel art apps. the app keeps
+//drawing, and getting input, in its own pixels
+pub const WindowSetScale_message: &str = "window-set-scale";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowSetScale {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+    pub scale:u32,
+}
+
 pub const SetScreenSize_message: &str = "set_screen_size";
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SetScreenSize {
@@ -301,3 +413,90 @@ pub struct SetScreenSize {
     pub width:i64,
     pub height:i64,
 }
+
+pub const WindowCopyRect_message: &str = "window-copy-rect";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowCopyRect {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+    //source rect, in window coordinates
+    pub x:i64,
+    pub y:i64,
+    pub width:i64,
+    pub height:i64,
+    //destination of the top left corner. may overlap the source
+    pub dx:i64,
+    pub dy:i64,
+}
+
+//opt in to double buffering. once enabled, drawing goes to a back buffer
+//which is only shown when the app sends a commit
+pub const WindowSetDoubleBuffered_message: &str = "window-set-double-buffered";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowSetDoubleBuffered {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+    pub enabled:bool,
+}
+
+pub const WindowCommit_message: &str = "window-commit";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WindowCommit {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub window:String,
+}
+
+//sent right after ScreenStart so the server knows what this screen can do
+pub const ScreenCapabilities_message: &str = "screen-capabilities";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScreenCapabilities {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub protocol_version:i64,
+    pub message_types:Vec<String>,
+    pub image_encodings:Vec<String>,
+    pub input_events:Vec<String>,
+    //real pixels per virtual pixel. can be fractional, it was an integer before protocol version 3
+    pub scale:f64,
+    pub color_depth:i64,
+    pub client_name:String,
+    pub client_version:String,
+}
+
+//the server's half, sent in the `capabilities` field of Connected.
+//an empty message_types list means the server didn't say, so assume everything
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ServerCapabilities {
+    #[serde(default)]
+    pub protocol_version:i64,
+    #[serde(default)]
+    pub message_types:Vec<String>,
+    //the binary framing version the server will send, 0 if it only sends json
+    #[serde(default)]
+    pub binary_protocol:i64,
+    //the DrawImage encodings the server will send, besides the json array
+    #[serde(default)]
+    pub image_encodings:Vec<String>,
+}
+
+//sent when the screen is shutting down, just before the websocket close
+pub const ScreenStop_message: &str = "screen-stop";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScreenStop {
+    #[serde(rename = "type")]
+    pub type_:String,
+}
+
+//tells the app that owns a window that one of its messages could not be used
+pub const ScreenError_message: &str = "screen-error";
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScreenError {
+    #[serde(rename = "type")]
+    pub type_:String,
+    pub target:String,
+    pub window:String,
+    pub message:String,
+}
@@ -0,0 +1,194 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread;
+use std::time::{Duration, Instant};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::runtime::Builder;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver};
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use idealos_schemas::general::{Connected_name, ScreenStart_name};
+use idealos_schemas::windows::WindowOpenDisplay_name;
+use idealos_schemas::graphics::{DrawRect_name, DrawPixel_name};
+use crate::binary::BINARY_PROTOCOL_VERSION;
+use crate::connection::PROTOCOL_VERSION;
+use crate::messages::{WindowList_message, CloseWindow_message, Group_message};
+
+//a stand in for the idealos server, for integration tests. it speaks just enough of the
+//protocol for the screen: it answers ScreenStart with Connected and its capabilities, sends
+//whatever the test scripts, and records every message the screen sends back.
+//one screen can be connected at a time. when it goes away the next one is accepted.
+pub struct MockServer {
+    pub addr:SocketAddr,
+    commands:UnboundedSender<Command>,
+    received:Arc<(Mutex<Vec<Value>>, Condvar)>,
+}
+
+enum Command {
+    Send(Message),
+    //stop reading from the screen
+    Hang,
+}
+
+impl MockServer {
+    //listens on a free port on localhost, on its own thread. it agrees to everything the screen offers
+    pub fn start() -> Result<MockServer, String> {
+        MockServer::start_with_capabilities(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "binary_protocol": BINARY_PROTOCOL_VERSION,
+            "image_encodings": ["raw", "png", "qoi", "indexed"],
+        }))
+    }
+    //caps go in the capabilities field of the Connected reply
+    pub fn start_with_capabilities(caps:Value) -> Result<MockServer, String> {
+        let runtime = Builder::new_current_thread().enable_all().build().map_err(|e|e.to_string())?;
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).map_err(|e|e.to_string())?;
+        let addr = listener.local_addr().map_err(|e|e.to_string())?;
+        let (commands, mut command_rx) = unbounded_channel::<Command>();
+        let received = Arc::new((Mutex::new(vec![]), Condvar::new()));
+        let recorder = received.clone();
+        thread::spawn(move || {
+            runtime.block_on(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    match accept_async(stream).await {
+                        Ok(ws) => serve(ws, &caps, &mut command_rx, &recorder).await,
+                        Err(e) => info!("mock server handshake failed: {}", e),
+                    }
+                }
+            });
+        });
+        Ok(MockServer { addr, commands, received })
+    }
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+    //queued until a screen is connected
+    pub fn send(&self, msg:Value) {
+        let _ = self.commands.send(Command::Send(Message::Text(msg.to_string())));
+    }
+    pub fn send_binary(&self, data:Vec<u8>) {
+        let _ = self.commands.send(Command::Send(Message::Binary(data)));
+    }
+    pub fn open_window(&self, id:&str, x:i64, y:i64, width:i64, height:i64) {
+        self.send(json!({
+            "type": WindowOpenDisplay_name,
+            "target": "screen",
+            "window": window_json(id, x, y, width, height),
+        }));
+    }
+    //windows is (id, x, y, width, height)
+    pub fn window_list(&self, windows:&[(&str, i64, i64, i64, i64)]) {
+        let mut map = serde_json::Map::new();
+        for (id, x, y, width, height) in windows {
+            map.insert(id.to_string(), window_json(id, *x, *y, *width, *height));
+        }
+        self.send(json!({ "type": WindowList_message, "windows": map }));
+    }
+    pub fn close_window(&self, id:&str) {
+        self.send(json!({
+            "type": CloseWindow_message,
+            "target": "screen",
+            "window": window_json(id, 0, 0, 1, 1),
+        }));
+    }
+    pub fn fill_rect(&self, window:&str, color:&str, x:i64, y:i64, width:i64, height:i64) {
+        self.send(json!({
+            "type": DrawRect_name, "window": window, "color": color,
+            "x": x, "y": y, "width": width, "height": height,
+        }));
+    }
+    pub fn draw_pixel(&self, window:&str, color:&str, x:i64, y:i64) {
+        self.send(json!({ "type": DrawPixel_name, "window": window, "color": color, "x": x, "y": y }));
+    }
+    pub fn group(&self, messages:Vec<Value>) {
+        self.send(json!({ "type": Group_message, "category": "test", "messages": messages }));
+    }
+    //closes the websocket, like the server going away
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(Command::Send(Message::Close(None)));
+    }
+    //stops reading from the screen, so its pings go unanswered, like a server that has hung.
+    //the connection stays open
+    pub fn hang(&self) {
+        let _ = self.commands.send(Command::Hang);
+    }
+    //everything the screen has sent so far, in order
+    pub fn received(&self) -> Vec<Value> {
+        self.received.0.lock().unwrap().clone()
+    }
+    pub fn received_of_type(&self, msg_type:&str) -> Vec<Value> {
+        self.received().into_iter().filter(|m| m["type"] == msg_type).collect()
+    }
+    //the first message of this type the screen sent, waiting for it if need be
+    pub fn wait_for(&self, msg_type:&str, timeout:Duration) -> Option<Value> {
+        self.wait_for_nth(msg_type, 0, timeout)
+    }
+    pub fn wait_for_nth(&self, msg_type:&str, n:usize, timeout:Duration) -> Option<Value> {
+        let deadline = Instant::now() + timeout;
+        let (lock, changed) = &*self.received;
+        let mut received = lock.lock().unwrap();
+        loop {
+            if let Some(m) = received.iter().filter(|m| m["type"] == msg_type).nth(n) {
+                return Some(m.clone());
+            }
+            let wait = deadline.checked_duration_since(Instant::now())?;
+            received = changed.wait_timeout(received, wait).unwrap().0;
+        }
+    }
+}
+
+fn window_json(id:&str, x:i64, y:i64, width:i64, height:i64) -> Value {
+    json!({
+        "id": id, "x": x, "y": y, "width": width, "height": height,
+        "owner": "mock-app", "window_type": "PLAIN",
+    })
+}
+
+async fn serve(ws:tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
+               caps:&Value,
+               commands:&mut UnboundedReceiver<Command>,
+               received:&(Mutex<Vec<Value>>, Condvar)) {
+    let (mut out, mut incoming) = ws.split();
+    let mut hung = false;
+    loop {
+        tokio::select! {
+            msg = incoming.next(), if !hung => {
+                let txt = match msg {
+                    Some(Ok(Message::Text(txt))) => txt,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                    //pings are answered by tungstenite
+                    Some(Ok(_)) => continue,
+                };
+                let v:Value = match serde_json::from_str(&txt) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        info!("mock server got bad json {}: {}", txt, e);
+                        continue;
+                    }
+                };
+                if v["type"] == ScreenStart_name {
+                    let connected = json!({ "type": Connected_name, "capabilities": caps });
+                    if out.send(Message::Text(connected.to_string())).await.is_err() { return; }
+                }
+                let (lock, changed) = received;
+                lock.lock().unwrap().push(v);
+                changed.notify_all();
+            }
+            cmd = commands.recv() => {
+                match cmd {
+                    Some(Command::Send(Message::Close(frame))) => {
+                        let _ = out.send(Message::Close(frame)).await;
+                        return;
+                    }
+                    Some(Command::Send(msg)) => {
+                        if out.send(msg).await.is_err() { return; }
+                    }
+                    Some(Command::Hang) => hung = true,
+                    None => return,
+                }
+            }
+        }
+    }
+}
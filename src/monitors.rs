@@ -0,0 +1,64 @@
+use sdl2::rect::Rect;
+use crate::window::{Bounds, Insets, Point};
+
+//how close to a monitor's edge a dragged window has to be let go to snap to it
+pub const SNAP_DISTANCE:i32 = 8;
+
+//the smallest rect holding every display, in the desktop coordinates SDL gives display bounds in
+pub fn span(displays:&[Rect]) -> Option<Rect> {
+    let first = *displays.first()?;
+    Some(displays.iter().skip(1).fold(first, |all, display| all.union(*display)))
+}
+
+//each display's part of a screen whose top left is at origin, in virtual pixels.
+//edges round up, like the screen's own size, so neighbouring monitors meet exactly
+pub fn monitor_bounds(displays:&[Rect], origin:(i32, i32), scale:f32) -> Vec<Bounds> {
+    let edge = |real:i32| (real as f32 / scale).ceil() as i32;
+    displays.iter().map(|d| {
+        let x = edge(d.x() - origin.0);
+        let y = edge(d.y() - origin.1);
+        Bounds {
+            x,
+            y,
+            width: edge(d.x() + d.width() as i32 - origin.0) - x,
+            height: edge(d.y() + d.height() as i32 - origin.1) - y,
+        }
+    }).collect()
+}
+
+//the monitor a point is on, or the nearest one if it is in a gap between them
+pub fn monitor_at(monitors:&[Bounds], pt:&Point) -> Option<Bounds> {
+    monitors.iter().min_by_key(|m| distance(m, pt)).copied()
+}
+
+//where a window's contents go so that, with its frame, it fills the area
+pub fn fill(area:&Bounds, frame:&Insets) -> Bounds {
+    Bounds {
+        x: area.x + frame.left,
+        y: area.y + frame.top,
+        width: area.width - frame.left - frame.right,
+        height: area.height - frame.top - frame.bottom,
+    }
+}
+
+//the part of a monitor a window let go of at pt snaps to: all of it at the top edge,
+//the left or right half at those edges. None away from the edges
+pub fn snap_area(monitor:&Bounds, pt:&Point) -> Option<Bounds> {
+    let half = monitor.width / 2;
+    if pt.y < monitor.y + SNAP_DISTANCE {
+        Some(*monitor)
+    } else if pt.x < monitor.x + SNAP_DISTANCE {
+        Some(Bounds { x: monitor.x, y: monitor.y, width: half, height: monitor.height })
+    } else if pt.x >= monitor.x + monitor.width - SNAP_DISTANCE {
+        Some(Bounds { x: monitor.x + half, y: monitor.y, width: monitor.width - half, height: monitor.height })
+    } else {
+        None
+    }
+}
+
+//0 inside
+fn distance(m:&Bounds, pt:&Point) -> i32 {
+    let dx = (m.x - pt.x).max(pt.x - (m.x + m.width - 1)).max(0);
+    let dy = (m.y - pt.y).max(pt.y - (m.y + m.height - 1)).max(0);
+    dx + dy
+}
@@ -0,0 +1,59 @@
+use crate::messages::{RenderMessage, any_graphics_message};
+
+//window ids are only unique within one server, so the screen keys windows by
+//connection and id. the connection is a number, so the first '/' ends it.
+pub fn window_key(connection:usize, id:&str) -> String {
+    format!("{}/{}", connection, id)
+}
+
+//the id the server knows the window by
+pub fn remote_id(key:&str) -> &str {
+    match key.find('/') {
+        Some(n) => &key[n+1..],
+        None => key,
+    }
+}
+
+//rewrite every window id in a message from `connection` into a window key
+pub fn namespace_message(msg:&mut RenderMessage, connection:usize) {
+    let key = |id:&mut String| *id = window_key(connection, id);
+    match msg {
+        RenderMessage::OpenWindow(m) => key(&mut m.window.id),
+        RenderMessage::WindowSetSize(m) => key(&mut m.window),
+        RenderMessage::WindowSetPosition(m) => key(&mut m.window),
+        RenderMessage::CloseWindow(m) => key(&mut m.window.id),
+        RenderMessage::CreateChildWindow(m) => {
+            key(&mut m.parent);
+            key(&mut m.window.id);
+        }
+        RenderMessage::CloseChildWindow(m) => key(&mut m.window),
+        RenderMessage::WindowList(m) => {
+            m.windows = m.windows.drain().map(|(id, mut info)| {
+                key(&mut info.id);
+                (window_key(connection, &id), info)
+            }).collect();
+        }
+        RenderMessage::DrawPixel(m) => key(&mut m.window),
+        RenderMessage::DrawImage(m) => key(&mut m.window),
+        RenderMessage::FillRect(m) => key(&mut m.window),
+        RenderMessage::CopyRect(m) => key(&mut m.window),
+        RenderMessage::SetDoubleBuffered(m) => key(&mut m.window),
+        RenderMessage::SetOpacity(m) => key(&mut m.window),
+        RenderMessage::SetMinimized(m) => key(&mut m.window),
+        RenderMessage::SetScale(m) => key(&mut m.window),
+        RenderMessage::Commit(m) => key(&mut m.window),
+        RenderMessage::Group(m) => {
+            for member in m.messages.iter_mut() {
+                match member {
+                    any_graphics_message::MAKE_DrawPixel_name { window, .. } => key(window),
+                    any_graphics_message::MAKE_DrawRect_name { window, .. } => key(window),
+                    any_graphics_message::MAKE_DrawImage_name { window, .. } => key(window),
+                    any_graphics_message::CopyRect { window, .. } => key(window),
+                    any_graphics_message::Commit { window } => key(window),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,36 +1,45 @@
-use websocket::{OwnedMessage, Message};
-use std::sync::mpsc::Receiver;
-use websocket::sender::Writer;
-use std::net::TcpStream;
+use futures_util::SinkExt;
+use futures_util::stream::SplitSink;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval_at, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use crate::connection::WsStream;
+use crate::heartbeat::Heartbeat;
 
-pub fn process_outgoing(websocket_sending_rx: &Receiver<OwnedMessage>, sender: &mut Writer<TcpStream>) {
+//send queued messages to the server, and ping it on the heartbeat interval.
+//returns why it stopped.
+pub async fn process_outgoing(websocket_sending_rx: &mut UnboundedReceiver<Message>, sender: &mut SplitSink<WsStream, Message>, heartbeat: &Heartbeat) -> String {
+    let mut pings = interval_at(Instant::now() + heartbeat.interval, heartbeat.interval);
     loop {
-        // Send loop
-        let message = match websocket_sending_rx.recv() {
-            Ok(m) => m,
-            Err(e) => {
-                println!("Send Loop: {:?}", e);
-                return;
+        tokio::select! {
+            _ = pings.tick() => {
+                if heartbeat.is_dead() {
+                    info!("server hasn't answered for {:?}, disconnecting", heartbeat.timeout);
+                    let _ = sender.send(Message::Close(None)).await;
+                    return format!("server stopped answering for {:?}", heartbeat.timeout);
+                }
+                if let Err(e) = sender.send(heartbeat.ping()).await {
+                    debug!("Send Loop: {:?}", e);
+                    return format!("connection error: {}", e);
+                }
             }
-        };
-        match message {
-            OwnedMessage::Close(_) => {
-                let _ = sender.send_message(&message);
+            message = websocket_sending_rx.recv() => {
+                let message = match message {
+                    Some(m) => m,
+                    //the render loop is gone
+                    None => return "screen closed".to_string(),
+                };
                 // If it's a close message, just send it and then return.
-                return;
-            }
-            _ => (),
-        }
-        // Send the message
-        // println!("sending out {:?}",message);
-        match sender.send_message(&message) {
-            Ok(()) => (),
-            Err(e) => {
-                println!("Send Loop: {:?}", e);
-                let _ = sender.send_message(&Message::close());
-                return;
+                if let Message::Close(_) = message {
+                    let _ = sender.send(message).await;
+                    return "screen closed".to_string();
+                }
+                // println!("sending out {:?}",message);
+                if let Err(e) = sender.send(message).await {
+                    debug!("Send Loop: {:?}", e);
+                    return format!("connection error: {}", e);
+                }
             }
         }
     }
-
 }
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use serde::de::Error as _;
+use serde_json::Value;
+use idealos_schemas::windows::{WindowOpenDisplay_name, WindowOpenDisplay, create_child_window_display_name, create_child_window_display, close_child_window_display_name, close_child_window_display};
+use idealos_schemas::graphics::{DrawPixel_name, DrawPixel, DrawRect_name, DrawRect, DrawImage_name};
+use idealos_schemas::general::Connected_name;
+
+use crate::error::{ClientError, ClientResult};
+use crate::imagedecode::decode_draw_image;
+use crate::messages::{RenderMessage, CloseWindowScreen, CloseWindow_message, window_list_message, WindowList_message, raw_group_message, Group_message, WindowSetSizeRequest, WindowSetSizeRequest_message, WindowSetPositionRequest, WindowSetPositionRequest_message, WindowCopyRect, WindowCopyRect_message, WindowSetDoubleBuffered, WindowSetDoubleBuffered_message, WindowCommit, WindowCommit_message, ServerCapabilities, DesktopSetWallpaper_message, WindowSetOpacity, WindowSetOpacity_message, WindowSetMinimized, WindowSetMinimized_message, WindowSetScale, WindowSetScale_message};
+use crate::incoming::parse_group;
+use crate::desktop::{parse_wallpaper, DESKTOP};
+use crate::connection::Negotiated;
+
+//turns one message of its type into what the render loop should do about it, within what was
+//agreed with the server. Ok(None) means the message was fine but there is nothing to render.
+pub type MessageHandler = Box<dyn Fn(Value, &Negotiated) -> ClientResult<Option<RenderMessage>> + Send + Sync>;
+
+//maps message types to their handlers. the text of a message is parsed once, and the handler for
+//its type is given the parsed value to take apart into the typed message, without copying it.
+pub struct MessageRegistry {
+    handlers:HashMap<String, MessageHandler>,
+}
+
+impl MessageRegistry {
+    pub fn new() -> MessageRegistry {
+        MessageRegistry { handlers: HashMap::new() }
+    }
+    //registering a type twice replaces the earlier handler
+    pub fn register<F>(&mut self, msg_type:&str, handler:F)
+        where F: Fn(Value, &Negotiated) -> ClientResult<Option<RenderMessage>> + Send + Sync + 'static {
+        self.handlers.insert(msg_type.to_string(), Box::new(handler));
+    }
+    //the common case: deserialize to T and wrap it in a render message
+    pub fn register_typed<T, F>(&mut self, msg_type:&str, wrap:F)
+        where T: DeserializeOwned, F: Fn(T) -> RenderMessage + Send + Sync + 'static {
+        self.register(msg_type, move |v, _| {
+            let msg:T = serde_json::from_value(v)?;
+            Ok(Some(wrap(msg)))
+        });
+    }
+    pub fn handles(&self, msg_type:&str) -> bool {
+        self.handlers.contains_key(msg_type)
+    }
+    //Ok(None) for messages nobody registered, so new server messages don't break old screens
+    pub fn parse(&self, txt:&str, agreed:&Negotiated) -> ClientResult<Option<RenderMessage>> {
+        let v:Value = serde_json::from_str(txt)?;
+        let handler = match &v {
+            Value::Object(fields) => match fields.get("type") {
+                Some(Value::String(msg_type)) => match self.handlers.get(msg_type) {
+                    Some(handler) => handler,
+                    None => {
+                        debug!("some other message type {}", msg_type);
+                        return Ok(None);
+                    }
+                },
+                Some(other) => return Err(ClientError::Parse(serde_json::Error::custom(format!("message type {} isn't a string", other)))),
+                None => return Err(ClientError::invalid("", "message has no type".to_string())),
+            },
+            _ => return Err(ClientError::Parse(serde_json::Error::custom("a message has to be a json object"))),
+        };
+        handler(v, agreed)
+    }
+    //every registered type, reported to the server at connect time
+    pub fn message_types(&self) -> Vec<String> {
+        let mut types:Vec<String> = self.handlers.keys().cloned().collect();
+        types.sort();
+        types
+    }
+}
+
+//everything the screen understands out of the box
+pub fn default_registry() -> MessageRegistry {
+    let mut reg = MessageRegistry::new();
+    reg.register(Connected_name, |mut v, _| {
+        debug!("really connected");
+        match v.get_mut("capabilities").map(Value::take) {
+            Some(caps) => {
+                let caps:ServerCapabilities = serde_json::from_value(caps)?;
+                Ok(Some(RenderMessage::ServerCapabilities(caps)))
+            }
+            None => Ok(None),
+        }
+    });
+    reg.register_typed(WindowOpenDisplay_name, |m:WindowOpenDisplay| RenderMessage::OpenWindow(m));
+    reg.register_typed(WindowList_message, |m:window_list_message| {
+        debug!("the window list is {:?}",m);
+        RenderMessage::WindowList(m)
+    });
+    //the whole group is one render message so it is applied within a single
+    //frame, and the translate and clip state can be applied in order
+    reg.register(Group_message, |v, agreed| {
+        let m:raw_group_message = serde_json::from_value(v)?;
+        Ok(Some(RenderMessage::Group(parse_group(m, agreed))))
+    });
+    reg.register_typed(WindowSetPositionRequest_message, |m:WindowSetPositionRequest| RenderMessage::WindowSetPosition(m));
+    reg.register_typed(WindowSetSizeRequest_message, |m:WindowSetSizeRequest| RenderMessage::WindowSetSize(m));
+    reg.register_typed(WindowCopyRect_message, |m:WindowCopyRect| RenderMessage::CopyRect(m));
+    reg.register_typed(WindowSetDoubleBuffered_message, |m:WindowSetDoubleBuffered| RenderMessage::SetDoubleBuffered(m));
+    reg.register_typed(WindowCommit_message, |m:WindowCommit| RenderMessage::Commit(m));
+    reg.register_typed(create_child_window_display_name, |m:create_child_window_display| RenderMessage::CreateChildWindow(m));
+    reg.register_typed(close_child_window_display_name, |m:close_child_window_display| RenderMessage::CloseChildWindow(m));
+    reg.register_typed(DrawPixel_name, |m:DrawPixel| RenderMessage::DrawPixel(m));
+    reg.register_typed(DrawRect_name, |m:DrawRect| RenderMessage::FillRect(m));
+    reg.register(DrawImage_name, |v, agreed| {
+        //decoding happens here on the network thread so big images don't stall rendering
+        let window = v["window"].as_str().unwrap_or("").to_string();
+        agreed.check_image(&v).map_err(|e|ClientError::invalid(&window, e))?;
+        let msg = decode_draw_image(v).map_err(|e|ClientError::invalid(&window, e))?;
+        Ok(Some(RenderMessage::DrawImage(msg)))
+    });
+    reg.register_typed(CloseWindow_message, |m:CloseWindowScreen| RenderMessage::CloseWindow(m));
+    reg.register_typed(WindowSetOpacity_message, |m:WindowSetOpacity| RenderMessage::SetOpacity(m));
+    reg.register_typed(WindowSetMinimized_message, |m:WindowSetMinimized| RenderMessage::SetMinimized(m));
+    reg.register_typed(WindowSetScale_message, |m:WindowSetScale| RenderMessage::SetScale(m));
+    reg.register(DesktopSetWallpaper_message, |v, agreed| {
+        if let Some(img) = v.get("image") {
+            agreed.check_image(img).map_err(|e|ClientError::invalid(DESKTOP, e))?;
+        }
+        Ok(Some(RenderMessage::SetWallpaper(parse_wallpaper(v)?)))
+    });
+    reg
+}
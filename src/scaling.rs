@@ -0,0 +1,45 @@
+use sdl2::hint;
+
+//the screen scale turns virtual pixels, which apps and the protocol use, into real pixels on
+//the display. it can be fractional, so sizes are rounded whichever way keeps the display covered
+
+//the dpi a scale of 1 is meant for
+pub const BASE_DPI:f32 = 96.0;
+
+//how far a window can magnify its contents
+pub const MAX_WINDOW_SCALE:u32 = 8;
+
+//a scale for a display with this many dots per inch, in steps of a half and never below 1
+pub fn scale_for_dpi(dpi:f32) -> f32 {
+    if !dpi.is_finite() || dpi <= 0.0 { return 1.0; }
+    ((dpi / BASE_DPI * 2.0).round() / 2.0).max(1.0)
+}
+
+//the virtual size of a display this many real pixels across. rounded up, so the pixels at
+//the right and bottom edges still belong to a virtual pixel rather than being left blank
+pub fn virtual_size(real:(u32, u32), scale:f32) -> (u32, u32) {
+    ((real.0 as f32 / scale).ceil() as u32, (real.1 as f32 / scale).ceil() as u32)
+}
+
+//the real size of a host window showing this many virtual pixels
+pub fn real_size(virt:(u32, u32), scale:f32) -> (u32, u32) {
+    ((virt.0 as f32 * scale).round() as u32, (virt.1 as f32 * scale).round() as u32)
+}
+
+//a real coordinate to the virtual pixel it falls in
+pub fn to_virtual(real:f32, scale:f32) -> i32 {
+    (real / scale).floor() as i32
+}
+
+pub fn check_scale(scale:f32) -> Result<f32, String> {
+    if scale.is_finite() && scale > 0.0 {
+        Ok(scale)
+    } else {
+        Err(format!("scale must be more than 0, not {}", scale))
+    }
+}
+
+//magnified pixels stay crisp squares rather than being smoothed. applies to textures made after it
+pub fn use_nearest_filtering() {
+    hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+}
@@ -1,39 +1,62 @@
-use std::time::Duration;
-use std::sync::mpsc::{Sender, Receiver};
+use std::time::{Duration, Instant};
 use std::collections::HashMap;
-use websocket::OwnedMessage;
+use tokio_tungstenite::tungstenite::Message;
 use serde_json::{json};
 
-use crate::window::{Window, Point, Insets, Bounds, Dimensions};
-use crate::messages::{RenderMessage, MouseDown, MouseDown_name, MouseUp, MouseUp_name, set_focused_window_message, KeyboardDown, KeyboardDown_name, WindowSetPosition_message, WindowSetPosition, WindowSetSize, WindowSetSize_message};
+use crate::window::{Window, Point, Bounds, Dimensions, WINDOW_TYPE_PLAIN};
+use crate::messages::{RenderMessage, MouseDown, MouseDown_name, MouseUp, MouseUp_name, set_focused_window_message, KeyboardDown, KeyboardDown_name, WindowSetPosition_message, WindowSetPosition, WindowSetSize, WindowSetSize_message, WindowCopyRect, group_message, any_graphics_message, WindowSetDoubleBuffered, SetFocusedWindow_name, ScreenError, ScreenError_message, WindowCloseRequest, WindowCloseRequest_message, WindowSetMinimized, WindowSetMinimized_message, SetScreenSize, SetScreenSize_message};
+use crate::error::{ClientError, ClientResult};
+use crate::color::lookup_color;
+use crate::validate::{validate_render_message, message_window};
+use crate::namespace::namespace_message;
+use crate::backend::ServerLink;
+use crate::config::{Settings, Effects};
+use crate::effects::{Animation, AnimationKind, shadow_layers};
+use crate::scaling::{virtual_size, to_virtual};
+use crate::monitors::{monitor_at, snap_area, fill, span, monitor_bounds};
+use crate::theme::{Theme, ThemeWatcher};
+use crate::desktop::{Desktop, WallpaperUpdate, wallpaper_rects};
+use serde::Serialize;
+use idealos_schemas::graphics::{DrawRect, DrawImage};
 use crate::fontinfo::FontInfo;
 
 
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::render::{WindowCanvas, Texture, TextureCreator, Canvas, RenderTarget};
+use sdl2::render::{WindowCanvas, Texture, TextureCreator, Canvas, RenderTarget, BlendMode};
 use sdl2::Sdl;
 use crate::common::send_refresh_all_windows_request;
 use sdl2::video::WindowContext;
 use sdl2::rect::Rect;
 use sdl2::mouse::{MouseButton, MouseState};
-use colors_transform::{Rgb, Color as CTColor};
 use crate::font::{FontInfo2, GlyphInfo};
 use sdl2::surface::Surface;
+use image::RgbaImage;
 
 // const SCALE: u32 = 1;
 // const SCALEI: i32 = SCALE as i32;
-const BORDER:Insets = Insets {
-    left: 1,
-    right: 1,
-    top: 10,
-    bottom: 1,
-};
-const RESIZE:Dimensions = Dimensions {
-    width: 10,
-    height: 10
-};
+//how much the opacity keys change the focused window's opacity, out of 255
+const OPACITY_STEP: i32 = 25;
+//how many messages each server gets handled per frame. the rest wait for the next frame, so a
+//server sending a flood can't starve the other servers or hold up drawing
+pub const MESSAGES_PER_FRAME: usize = 1000;
+
+//why the render loop stopped
+pub enum LoopExit {
+    Quit,
+    Disconnected(String),
+}
+
+//how far process_render_messages got
+pub enum Processed {
+    //every queue is empty
+    Drained,
+    //a server used up its MESSAGES_PER_FRAME, there may be more waiting
+    Backlog,
+    //the last server has gone away, and why
+    AllGone(String),
+}
 
 pub struct SDL2Backend<'a> {
     pub active_window:Option<String>,
@@ -41,322 +64,920 @@ pub struct SDL2Backend<'a> {
     pub canvas: WindowCanvas,
     pub creator: &'a TextureCreator<WindowContext>,
     pub window_buffers:HashMap<String,Texture<'a>>,
+    //only present for windows which opted in to double buffering
+    pub back_buffers:HashMap<String,Texture<'a>>,
     pub window_order:Vec<String>,
     pub dragging:bool,
     pub resizing:bool,
     pub dragtarget:Option<String>,
     pub font_info:FontInfo2,
-    pub scale:u32,
-    pub iscale:i32,
+    //virtual pixels to real ones, and maybe fractional. see scaling.rs
+    pub scale:f32,
+    //the renderer's size in real pixels, as the servers were last told it
+    pub screen_size:(u32, u32),
+    //the displays a spanning screen covers, in virtual pixels. empty when there is one
+    //display, which is then the whole screen
+    pub monitors:Vec<Bounds>,
+    //where maximized and snapped windows go back to
+    pub restore_bounds:HashMap<String,Bounds>,
+    //one per server. Window.connection indexes into this
+    pub servers:Vec<ServerLink>,
+    //toggled with the debug overlay key, F12 unless configured otherwise
+    pub show_debug:bool,
+    pub settings:Settings,
+    //set it with set_theme(), which also resets the desktop
+    pub theme:Theme,
+    pub theme_watcher:Option<ThemeWatcher>,
+    pub desktop:Desktop<'a>,
+    //windows part way through opening, minimizing or restoring
+    pub animations:HashMap<String,Animation>,
+    //windows that are gone but still fading out
+    pub closing:Vec<ClosingWindow<'a>>,
+}
+
+//a closed window and its last contents
+pub struct ClosingWindow<'a> {
+    pub win:Window,
+    pub tex:Texture<'a>,
+    pub animation:Animation,
 }
 
 
 impl<'a> SDL2Backend<'a> {
-    fn process_render_messages(&mut self,
+    //handle up to MESSAGES_PER_FRAME messages from each server, taking turns
+    pub fn process_render_messages(&mut self,
                                windows:&mut HashMap<String, Window>,
-                               input: &Receiver<RenderMessage>,
-                               output: &Sender<OwnedMessage>,
-    ) {
-        'main: loop {
-            match input.try_recv() {
-                Ok(msg) => {
-                    // println!("incoming message {:?}",msg);
-                    match msg {
-                        RenderMessage::OpenWindow(m) => {
-                            println!("opening a window {:?}",m);
-                            let win:Window = Window {
-                                id: m.window.id.clone(),
-                                x: m.window.x as i32,
-                                y: m.window.y as i32,
-                                width: m.window.width as i32,
-                                height: m.window.height as i32,
-                                owner: m.window.owner.clone(),
-                                window_type: m.window.window_type.clone(),
-                                title: "title".to_string()
-                            };
-                            self.init_window(&win);
-                            // self.window_buffers.insert(win.id.clone(),win);
-                            windows.insert(m.window.id.clone(), win);
-                            // println!("window count is {}", windows.len());
-                        }
-                        RenderMessage::WindowSetSize(m) => {
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                win.width = m.width as i32;
-                                win.height = m.height as i32;
-                                self.resize_window(win);
-                                let size_msg = WindowSetSize {
-                                    type_: WindowSetSize_message.to_string(),
-                                    app: String::from("rust_client"),
-                                    window: win.id.to_string(),
-                                    width: win.width as i64,
-                                    height: win.height as i64,
-                                };
-                                output.send(OwnedMessage::Text(json!(size_msg).to_string()));
-                            }
-                        }
-                        RenderMessage::WindowSetPosition(m) => {
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                win.x = m.x as i32;
-                                win.y = m.y as i32;
-
-                                let pt = Point { x: m.x as i32, y: m.y as i32 };
-                                let move_msg = WindowSetPosition {
-                                    type_: WindowSetPosition_message.to_string(),
-                                    app: String::from("rust-client"),
-                                    window: win.id.to_string(),
-                                    x: pt.x as i64,
-                                    y: pt.y as i64,
-                                };
-                                // println!("setting window position {:?}",move_msg);
-                                output.send(OwnedMessage::Text(json!(move_msg).to_string()));
-                            }
-                        }
-                        RenderMessage::CreateChildWindow(m) => {
-                            // println!("creating a child window");
-                            if let Some(win) = windows.get_mut(&m.parent) {
-                                let child:Window = Window {
-                                    id:m.window.id.clone(),
-                                    x: m.window.x as i32,
-                                    y: m.window.y as i32,
-                                    width: m.window.width as i32,
-                                    height: m.window.height as i32,
-                                    owner: m.window.owner.clone(),
-                                    window_type: m.window.window_type.clone(),
-                                    title: "title".to_string()
-                                };
-
-                                self.init_window(&child);
-                                windows.insert(child.id.clone(),  child);
-                            }
+    ) -> Processed {
+        let mut processed = Processed::Drained;
+        for connection in 0..self.servers.len() {
+            for handled in 0..=MESSAGES_PER_FRAME {
+                if !self.servers[connection].open { break; }
+                if handled == MESSAGES_PER_FRAME {
+                    processed = Processed::Backlog;
+                    break;
+                }
+                match self.servers[connection].input.try_recv() {
+                    Ok(mut msg) => {
+                        if self.settings.log_messages {
+                            info!("incoming message from {}: {:?}", self.servers[connection].label, msg);
                         }
-                        RenderMessage::CloseChildWindow(m) => {
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                self.close_window(win);
-                                windows.remove(m.window.as_str());
+                        if let RenderMessage::Disconnected(reason) = msg {
+                            self.close_server(windows, connection, &reason);
+                            if self.servers.iter().all(|s| !s.open) {
+                                return Processed::AllGone(reason);
                             }
+                            break;
                         }
-                        RenderMessage::WindowList(m) => {
-                            // println!("window list");
-                            for (key, value) in &m.windows {
-                                // println!("make window id {} at {},{}", value.id, value.x, value.y);
-                                let win = Window::from_info2(&value);
-                                self.init_window(&win);
-                                windows.insert(win.id.clone(), win);
-                            }
-                            println!("window count is {:?}", windows.len());
-                            send_refresh_all_windows_request(&windows, &output);
-                        },
-                        RenderMessage::CloseWindow(m) => {
-                            // println!("closing a window {:?}",m);
-                            if let Some(win) = windows.get_mut(m.window.id.as_str()) {
-                                self.close_window(win);
-                                windows.remove(m.window.id.as_str());
-                            }
-                        },
-                        RenderMessage::DrawPixel(m) => {
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                if let Some(tex) = self.window_buffers.get_mut(win.id.as_str()) {
-                                    self.canvas.with_texture_canvas(tex, |texture_canvas| {
-                                        texture_canvas.set_draw_color(lookup_color(&m.color));
-                                        texture_canvas
-                                            .fill_rect(Rect::new(m.x as i32,
-                                                                 m.y as i32, 1, 1))
-                                            .expect("could not fill rect");
-                                        // println!("drew pixel to texture at {},{} c={}",m.x,m.y, m.color);
-                                    });
-                                }
-                            }
-                        },
-                        RenderMessage::FillRect(m) => {
-                            // println!("fill rect {:?}",m);
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                if let Some(tex) = self.window_buffers.get_mut(win.id.as_str()) {
-                                    self.canvas.with_texture_canvas(tex, |texture_canvas| {
-                                        texture_canvas.set_draw_color(lookup_color(&m.color));
-                                        texture_canvas
-                                            .fill_rect(Rect::new(m.x as i32, m.y as i32, m.width as u32, m.height as u32))
-                                            .expect("could not fill rect");
-                                        // println!("drew rect to texture at {},{} - {}x{}",m.x,m.y,m.width,m.height);
-                                    });
+                        namespace_message(&mut msg, connection);
+                        let target = message_window(&msg);
+                        match validate_render_message(&mut msg, windows) {
+                            Ok(skipped) => {
+                                for err in skipped {
+                                    self.report_error(windows, err.window().map(|w|w.to_string()), &err);
                                 }
-                            }
-                        }
-                        RenderMessage::DrawImage(m) => {
-                            if let Some(win) = windows.get_mut(m.window.as_str()) {
-                                if let Some(tex) = self.window_buffers.get_mut(win.id.as_str()) {
-                                    // println!("drawing an image {}x{}", m.width, m.height);
-                                    self.canvas.with_texture_canvas(tex,|texture_canvas|{
-                                        // println!("drew image to texture at {},{} - {}x{}, count={}",m.x,m.y,m.width,m.height,m.pixels.len());
-                                        for i in 0..m.width {
-                                            for j in 0..m.height {
-                                                let n:usize = ((j * m.width + i) * 4) as usize;
-                                                let alpha = m.pixels[n+3];
-                                                if m.depth == 8 {
-                                                    //if 8bit depth then it's a real RGBA image
-                                                    if alpha > 0 {
-                                                        let col = Color::RGBA(m.pixels[n + 0], m.pixels[n + 1], m.pixels[n + 2], m.pixels[n + 3]);
-                                                        texture_canvas.set_draw_color(col);
-                                                        texture_canvas.fill_rect(Rect::new((m.x + i) as i32, (m.y + j) as i32, 1, 1));
-                                                    }
-                                                } else if m.depth == 1 {
-                                                    //if 1bit depth and a color is set, then draw with that color wherever not transparent (alpha > 0)
-                                                    if alpha > 0 {
-                                                        let col = lookup_color(&m.color);
-                                                        texture_canvas.set_draw_color(col);
-                                                        texture_canvas.fill_rect(Rect::new((m.x + i) as i32, (m.y + j) as i32, 1, 1));
-                                                    }
-                                                    //else assume it's just black wherever not transparent (alpha > 0)
-                                                } else {
-                                                    if alpha > 0 {
-                                                        let col = Color::RGBA(m.pixels[n + 0], m.pixels[n + 1], m.pixels[n + 2], m.pixels[n + 3]);
-                                                        texture_canvas.set_draw_color(col);
-                                                        texture_canvas.fill_rect(Rect::new((m.x + i) as i32, (m.y + j) as i32, 1, 1));
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    });
+                                if let Err(err) = self.apply_render_message(windows, msg, connection) {
+                                    self.report_error(windows, target, &err);
                                 }
                             }
-                        }
-                        _ => {
-                            println!("unhandled message {:?}",msg);
+                            Err(err) => self.report_error(windows, target, &err),
                         }
                     }
+                    Err(_) => break
                 }
-                Err(e) => break
             }
         }
+        processed
     }
-    fn init_window(&mut self, win: &Window) {
-        let mut tex = self.creator.create_texture_target(PixelFormatEnum::RGBA8888,
-                                                         win.width as u32,
-                                                         win.height as u32
-                                                         // 256,256
-        )
-            .map_err(|e|e.to_string()).unwrap();
-        println!("made texture {}x{}",win.width, win.height);
-        self.canvas.with_texture_canvas(&mut tex, |tc|{
-            tc.clear();
-            tc.set_draw_color(Color::RGBA(0,0,0,255));
-            tc
-                .fill_rect(Rect::new(0, 0, (win.width as u32) as u32, (win.height as u32) as u32));
-        });
+    //a server went away, so its windows go too
+    fn close_server(&mut self, windows: &mut HashMap<String, Window>, connection: usize, reason: &str) {
+        info!("lost {}: {}", self.servers[connection].label, reason);
+        self.servers[connection].open = false;
+        let ids:Vec<String> = windows.values().filter(|w| w.connection == connection).map(|w| w.id.clone()).collect();
+        for id in ids {
+            if let Some(mut win) = windows.remove(&id) {
+                self.close_window(&mut win);
+            }
+            if self.active_window.as_ref() == Some(&id) { self.active_window = None; }
+            if self.dragtarget.as_ref() == Some(&id) {
+                self.dragtarget = None;
+                self.dragging = false;
+                self.resizing = false;
+            }
+        }
+    }
+    fn apply_render_message(&mut self,
+                            windows:&mut HashMap<String, Window>,
+                            msg: RenderMessage,
+                            connection: usize,
+    ) -> ClientResult<()> {
+        match msg {
+            RenderMessage::OpenWindow(m) => {
+                debug!("opening a window {:?}",m);
+                let win:Window = Window {
+                    id: m.window.id.clone(),
+                    connection,
+                    x: m.window.x as i32,
+                    y: m.window.y as i32,
+                    width: m.window.width as i32,
+                    height: m.window.height as i32,
+                    owner: m.window.owner.clone(),
+                    window_type: m.window.window_type.clone(),
+                    title: "title".to_string(),
+                    ..Default::default()
+                };
+                self.init_window(&win)?;
+                if win.window_type == WINDOW_TYPE_PLAIN {
+                    self.animate(&win.id, AnimationKind::Open, self.settings.effects.open);
+                }
+                // self.window_buffers.insert(win.id.clone(),win);
+                windows.insert(m.window.id.clone(), win);
+                // debug!("window count is {}", windows.len());
+            }
+            RenderMessage::WindowSetSize(m) => {
+                if let Some(win) = windows.get_mut(m.window.as_str()) {
+                    win.width = m.width as i32;
+                    win.height = m.height as i32;
+                    self.resize_window(win)?;
+                    let size_msg = WindowSetSize {
+                        type_: WindowSetSize_message.to_string(),
+                        app: String::from("rust_client"),
+                        window: win.remote_id().to_string(),
+                        width: win.width as i64,
+                        height: win.height as i64,
+                    };
+                    self.send_to_server(win.connection, WindowSetSize_message, &size_msg);
+                }
+            }
+            RenderMessage::WindowSetPosition(m) => {
+                if let Some(win) = windows.get_mut(m.window.as_str()) {
+                    win.x = m.x as i32;
+                    win.y = m.y as i32;
+
+                    let pt = Point { x: m.x as i32, y: m.y as i32 };
+                    let move_msg = WindowSetPosition {
+                        type_: WindowSetPosition_message.to_string(),
+                        app: String::from("rust-client"),
+                        window: win.remote_id().to_string(),
+                        x: pt.x as i64,
+                        y: pt.y as i64,
+                    };
+                    // println!("setting window position {:?}",move_msg);
+                    self.send_to_server(win.connection, WindowSetPosition_message, &move_msg);
+                }
+            }
+            RenderMessage::CreateChildWindow(m) => {
+                // println!("creating a child window");
+                if let Some(win) = windows.get_mut(&m.parent) {
+                    let child:Window = Window {
+                        id:m.window.id.clone(),
+                        connection,
+                        x: m.window.x as i32,
+                        y: m.window.y as i32,
+                        width: m.window.width as i32,
+                        height: m.window.height as i32,
+                        owner: m.window.owner.clone(),
+                        window_type: m.window.window_type.clone(),
+                        title: "title".to_string(),
+                        ..Default::default()
+                    };
+
+                    self.init_window(&child)?;
+                    windows.insert(child.id.clone(),  child);
+                }
+            }
+            RenderMessage::CloseChildWindow(m) => {
+                if let Some(win) = windows.get_mut(m.window.as_str()) {
+                    self.close_window(win);
+                    windows.remove(m.window.as_str());
+                }
+            }
+            RenderMessage::WindowList(m) => {
+                // println!("window list");
+                for (key, value) in &m.windows {
+                    // println!("make window id {} at {},{}", value.id, value.x, value.y);
+                    let win = Window::from_info2(&value, connection);
+                    self.init_window(&win)?;
+                    windows.insert(win.id.clone(), win);
+                }
+                debug!("window count is {:?}", windows.len());
+                send_refresh_all_windows_request(&windows, connection, &self.servers[connection].output);
+            },
+            RenderMessage::CloseWindow(m) => {
+                // println!("closing a window {:?}",m);
+                if let Some(win) = windows.get_mut(m.window.id.as_str()) {
+                    self.close_window(win);
+                    windows.remove(m.window.id.as_str());
+                }
+            },
+            RenderMessage::DrawPixel(m) => {
+                self.draw_pixel(&m.window, &m.color, m.x as i32, m.y as i32, None)?;
+            },
+            RenderMessage::FillRect(m) => {
+                // println!("fill rect {:?}",m);
+                self.fill_rect(&m, None)?;
+            }
+            RenderMessage::DrawImage(m) => {
+                self.draw_image(&m, None)?;
+            }
+            RenderMessage::Group(m) => {
+                self.draw_group(windows, m)?;
+            }
+            RenderMessage::SetDoubleBuffered(m) => {
+                if let Some(win) = windows.get(m.window.as_str()) {
+                    self.set_double_buffered(win, &m)?;
+                }
+            }
+            RenderMessage::Commit(m) => {
+                self.commit_window(m.window.as_str())?;
+            }
+            RenderMessage::ServerCapabilities(m) => {
+                debug!("server protocol version {} supports {:?}", m.protocol_version, m.message_types);
+                self.servers[connection].caps = Some(m);
+            }
+            RenderMessage::SetWallpaper(m) => self.set_wallpaper(m)?,
+            RenderMessage::SetScale(m) => {
+                if let Some(win) = windows.get_mut(m.window.as_str()) {
+                    win.scale = m.scale;
+                }
+            }
+            RenderMessage::SetMinimized(m) => self.set_minimized(windows, &m.window, m.minimized),
+            RenderMessage::SetOpacity(m) => {
+                if let Some(win) = windows.get_mut(m.window.as_str()) {
+                    win.opacity = (m.opacity * 255.0).round() as u8;
+                }
+            }
+            RenderMessage::CopyRect(m) => {
+                if let Some(win) = windows.get(m.window.as_str()) {
+                    self.copy_rect(win, &m, None)?;
+                }
+            }
+            _ => {
+                debug!("unhandled message {:?}",msg);
+            }
+        }
+        Ok(())
+    }
+    //log the error, and tell the app that owns the window what it did wrong
+    fn report_error(&self, windows: &HashMap<String, Window>, window: Option<String>, err: &ClientError) {
+        info!("error handling message: {}", err);
+        if let Some(win) = window.as_ref().and_then(|id| windows.get(id)) {
+            let msg = ScreenError {
+                type_: ScreenError_message.to_string(),
+                target: win.owner.clone(),
+                window: win.remote_id().to_string(),
+                message: err.to_string(),
+            };
+            self.send_to_server(win.connection, ScreenError_message, &msg);
+        }
+    }
+    //older servers don't report their capabilities, so assume they take everything
+    fn server_accepts(&self, connection: usize, msg_type: &str) -> bool {
+        match &self.servers[connection].caps {
+            Some(caps) if !caps.message_types.is_empty() => caps.message_types.iter().any(|t| t == msg_type),
+            _ => true,
+        }
+    }
+    fn send_to_server<T: Serialize>(&self, connection: usize, msg_type: &str, msg: &T) {
+        if !self.server_accepts(connection, msg_type) {
+            return;
+        }
+        self.servers[connection].output.send(Message::Text(json!(msg).to_string()));
+    }
+    fn init_window(&mut self, win: &Window) -> ClientResult<()> {
+        let tex = self.make_buffer(win.width as u32, win.height as u32)?;
+        debug!("made texture {}x{}",win.width, win.height);
         self.window_buffers.insert(win.id.clone(),tex);
-        self.window_order.push(win.id.clone());
+        self.back_buffers.remove(win.id.as_str());
+        //reopening a window replaces it rather than stacking it twice
+        if !self.window_order.contains(&win.id) {
+            self.window_order.push(win.id.clone());
+        }
+        Ok(())
     }
-    fn resize_window(&mut self, win: &Window) {
+    fn resize_window(&mut self, win: &Window) -> ClientResult<()> {
         self.window_buffers.remove(win.id.as_str());
-        let mut tex = self.creator.create_texture_target(PixelFormatEnum::RGBA8888,win.width as u32, win.height as u32)
-            .map_err(|e|e.to_string()).unwrap();
+        let tex = self.make_buffer(win.width as u32, win.height as u32)?;
+        self.window_buffers.insert(win.id.clone(),tex);
+        if self.back_buffers.contains_key(win.id.as_str()) {
+            let back = self.make_buffer(win.width as u32, win.height as u32)?;
+            self.back_buffers.insert(win.id.clone(), back);
+        }
+        Ok(())
+    }
+    //a new window texture, filled with opaque black. it is composited with blending so
+    //the window's opacity applies
+    fn make_buffer(&mut self, width: u32, height: u32) -> ClientResult<Texture<'a>> {
+        let mut tex = self.creator.create_texture_target(PixelFormatEnum::RGBA8888, width, height)
+            .map_err(|e|ClientError::Texture(e.to_string()))?;
+        tex.set_blend_mode(BlendMode::Blend);
         self.canvas.with_texture_canvas(&mut tex, |tc|{
-            tc.clear();
             tc.set_draw_color(Color::RGBA(0,0,0,255));
-            tc
-                .fill_rect(Rect::new(0, 0, (win.width as u32) as u32, (win.height as u32) as u32));
-        });
-        self.window_buffers.insert(win.id.clone(),tex);
+            tc.clear();
+        }).map_err(|e|ClientError::Texture(e.to_string()))?;
+        Ok(tex)
+    }
+    fn set_double_buffered(&mut self, win: &Window, m: &WindowSetDoubleBuffered) -> ClientResult<()> {
+        if m.enabled {
+            if self.back_buffers.contains_key(win.id.as_str()) { return Ok(()); }
+            //start the back buffer from what is on screen now, so incremental drawing still works
+            let mut back = self.make_buffer(win.width as u32, win.height as u32)?;
+            if let Some(front) = self.window_buffers.get(win.id.as_str()) {
+                let mut res = Ok(());
+                self.canvas.with_texture_canvas(&mut back, |tc| {
+                    res = tc.copy(front, None, None);
+                }).map_err(|e|ClientError::Texture(e.to_string()))?;
+                res.map_err(ClientError::Texture)?;
+            }
+            self.back_buffers.insert(win.id.clone(), back);
+        } else {
+            //show whatever was pending, then go back to drawing straight to the screen
+            self.commit_window(win.id.as_str())?;
+            self.back_buffers.remove(win.id.as_str());
+        }
+        Ok(())
+    }
+    //copy the back buffer to the front. the back buffer keeps its contents so the
+    //app can keep drawing incrementally on top of the last frame.
+    fn commit_window(&mut self, id: &str) -> ClientResult<()> {
+        if let Some(back) = self.back_buffers.get(id) {
+            if let Some(front) = self.window_buffers.get_mut(id) {
+                let mut res = Ok(());
+                self.canvas.with_texture_canvas(front, |tc| {
+                    res = tc.copy(back, None, None);
+                }).map_err(|e|ClientError::Texture(e.to_string()))?;
+                res.map_err(ClientError::Texture)?;
+            }
+        }
+        Ok(())
+    }
+    fn draw_pixel(&mut self, window: &str, color: &String, x: i32, y: i32, clip: Option<Rect>) -> ClientResult<()> {
+        let color = lookup_color(color)?;
+        if let Some(tex) = draw_target(&mut self.back_buffers, &mut self.window_buffers, window) {
+            let mut res = Ok(());
+            self.canvas.with_texture_canvas(tex, |texture_canvas| {
+                texture_canvas.set_clip_rect(clip);
+                texture_canvas.set_blend_mode(fill_blend(color));
+                texture_canvas.set_draw_color(color);
+                res = texture_canvas.fill_rect(Rect::new(x, y, 1, 1));
+                texture_canvas.set_blend_mode(BlendMode::None);
+                texture_canvas.set_clip_rect(None);
+                // println!("drew pixel to texture at {},{} c={}",x,y,color);
+            }).map_err(|e|ClientError::Texture(e.to_string()))?;
+            res.map_err(ClientError::Texture)?;
+        }
+        Ok(())
+    }
+    fn fill_rect(&mut self, m: &DrawRect, clip: Option<Rect>) -> ClientResult<()> {
+        let color = lookup_color(&m.color)?;
+        if m.width == 0 || m.height == 0 { return Ok(()); }
+        if let Some(tex) = draw_target(&mut self.back_buffers, &mut self.window_buffers, m.window.as_str()) {
+            let mut res = Ok(());
+            self.canvas.with_texture_canvas(tex, |texture_canvas| {
+                texture_canvas.set_clip_rect(clip);
+                texture_canvas.set_blend_mode(fill_blend(color));
+                texture_canvas.set_draw_color(color);
+                res = texture_canvas.fill_rect(Rect::new(m.x as i32, m.y as i32, m.width as u32, m.height as u32));
+                texture_canvas.set_blend_mode(BlendMode::None);
+                texture_canvas.set_clip_rect(None);
+                // println!("drew rect to texture at {},{} - {}x{}",m.x,m.y,m.width,m.height);
+            }).map_err(|e|ClientError::Texture(e.to_string()))?;
+            res.map_err(ClientError::Texture)?;
+        }
+        Ok(())
+    }
+    //the pixel buffer length has already been checked by validate_render_message.
+    //the image goes through a texture so its alpha is blended over the window, like a fill
+    fn draw_image(&mut self, m: &DrawImage, clip: Option<Rect>) -> ClientResult<()> {
+        //1bit images are drawn in the message color wherever not transparent (alpha > 0)
+        let tint = if m.depth == 1 { Some(lookup_color(&m.color)?) } else { None };
+        if m.width == 0 || m.height == 0 { return Ok(()); }
+        let id = m.window.as_str();
+        if !self.back_buffers.contains_key(id) && !self.window_buffers.contains_key(id) { return Ok(()); }
+        let len = (m.width * m.height * 4) as usize;
+        let image = match tint {
+            Some(col) => {
+                let mut pixels = Vec::with_capacity(len);
+                for px in m.pixels[..len].chunks(4) {
+                    if px[3] > 0 {
+                        pixels.extend_from_slice(&[col.r, col.g, col.b, col.a]);
+                    } else {
+                        pixels.extend_from_slice(&[0, 0, 0, 0]);
+                    }
+                }
+                self.rgba_texture(m.width as u32, m.height as u32, &pixels)?
+            }
+            //8bit depth and anything else is a real RGBA image
+            None => self.rgba_texture(m.width as u32, m.height as u32, &m.pixels[..len])?,
+        };
+        if let Some(tex) = draw_target(&mut self.back_buffers, &mut self.window_buffers, id) {
+            let mut res = Ok(());
+            self.canvas.with_texture_canvas(tex,|texture_canvas|{
+                texture_canvas.set_clip_rect(clip);
+                res = texture_canvas.copy(&image, None, Rect::new(m.x as i32, m.y as i32, m.width as u32, m.height as u32));
+                texture_canvas.set_clip_rect(None);
+            }).map_err(|e|ClientError::Texture(e.to_string()))?;
+            res.map_err(ClientError::Texture)?;
+        }
+        Ok(())
+    }
+    //draw the members of a group in order, tracking the translation and clip stacks.
+    //clip rects are stored in window coordinates. a None entry means the clip is empty
+    //and nothing should be drawn until it is popped.
+    //the whole group is applied before the next present, so a partial group never reaches the screen.
+    //members drawing to unknown windows were already removed by validate_render_message.
+    fn draw_group(&mut self, windows: &HashMap<String, Window>, m: group_message) -> ClientResult<()> {
+        let mut offsets:Vec<Point> = vec![Point { x: 0, y: 0 }];
+        let mut clips:Vec<Option<Rect>> = vec![];
+        for sub_mess in m.messages {
+            let off = offsets.last().unwrap().clone();
+            let clip = clips.last().cloned();
+            match sub_mess {
+                any_graphics_message::Commit { window } => {
+                    self.commit_window(window.as_str())?;
+                }
+                any_graphics_message::PushTranslate { x, y } => {
+                    offsets.push(Point { x: off.x.saturating_add(x as i32), y: off.y.saturating_add(y as i32) });
+                }
+                any_graphics_message::PopTranslate => {
+                    if offsets.len() > 1 { offsets.pop(); }
+                }
+                any_graphics_message::PushClip { x, y, width, height } => {
+                    let rect = if width > 0 && height > 0 {
+                        Some(Rect::new(off.x.saturating_add(x as i32), off.y.saturating_add(y as i32), width as u32, height as u32))
+                    } else {
+                        None
+                    };
+                    let rect = match (clip, rect) {
+                        (None, r) => r,
+                        (Some(Some(parent)), Some(r)) => parent.intersection(r),
+                        _ => None,
+                    };
+                    clips.push(rect);
+                }
+                any_graphics_message::PopClip => {
+                    clips.pop();
+                }
+                any_graphics_message::MAKE_DrawPixel_name { window, color, x, y } => {
+                    if let Some(None) = clip { continue; }
+                    self.draw_pixel(&window, &color, off.x.saturating_add(x as i32), off.y.saturating_add(y as i32), clip.flatten())?;
+                }
+                any_graphics_message::CopyRect { window, x, y, width, height, dx, dy } => {
+                    if let Some(None) = clip { continue; }
+                    if let Some(win) = windows.get(window.as_str()) {
+                        self.copy_rect(win, &WindowCopyRect {
+                            type_: "".to_string(),
+                            window: window.clone(),
+                            x: x + off.x as i64,
+                            y: y + off.y as i64,
+                            width,
+                            height,
+                            dx: dx + off.x as i64,
+                            dy: dy + off.y as i64,
+                        }, clip.flatten())?;
+                    }
+                }
+                any_graphics_message::MAKE_DrawRect_name { window, color, x, y, width, height } => {
+                    if let Some(None) = clip { continue; }
+                    self.fill_rect(&DrawRect {
+                        type_: "".to_string(),
+                        window,
+                        color,
+                        x: x + off.x as i64,
+                        y: y + off.y as i64,
+                        width,
+                        height
+                    }, clip.flatten())?;
+                }
+                any_graphics_message::MAKE_DrawImage_name { window, color, x, y, width, height, depth, channels, pixels } => {
+                    if let Some(None) = clip { continue; }
+                    self.draw_image(&DrawImage {
+                        type_: "".to_string(),
+                        window,
+                        color,
+                        x: x + off.x as i64,
+                        y: y + off.y as i64,
+                        width,
+                        height,
+                        depth,
+                        channels,
+                        pixels
+                    }, clip.flatten())?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn copy_rect(&mut self, win: &Window, m: &WindowCopyRect, clip: Option<Rect>) -> ClientResult<()> {
+        if m.width <= 0 || m.height <= 0 { return Ok(()); }
+        //clip the source to the window so we never read outside of the texture
+        let bounds = Rect::new(0, 0, win.width as u32, win.height as u32);
+        let src = match Rect::new(m.x as i32, m.y as i32, m.width as u32, m.height as u32).intersection(bounds) {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let dst = Rect::new(src.x() + (m.dx - m.x) as i32,
+                            src.y() + (m.dy - m.y) as i32,
+                            src.width(), src.height());
+        //SDL can't copy a texture onto itself, so bounce through a scratch texture.
+        //this also makes overlapping source and destination rects safe.
+        let mut scratch = self.creator.create_texture_target(PixelFormatEnum::RGBA8888, src.width(), src.height())
+            .map_err(|e|ClientError::Texture(e.to_string()))?;
+        let source = match self.back_buffers.get(win.id.as_str()) {
+            Some(tex) => Some(tex),
+            None => self.window_buffers.get(win.id.as_str()),
+        };
+        if let Some(tex) = source {
+            self.canvas.with_texture_canvas(&mut scratch, |tc| {
+                tc.copy(tex, src, None);
+            }).map_err(|e|ClientError::Texture(e.to_string()))?;
+        }
+        if let Some(tex) = draw_target(&mut self.back_buffers, &mut self.window_buffers, win.id.as_str()) {
+            self.canvas.with_texture_canvas(tex, |tc| {
+                tc.set_clip_rect(clip);
+                tc.copy(&scratch, None, dst);
+                tc.set_clip_rect(None);
+            }).map_err(|e|ClientError::Texture(e.to_string()))?;
+        }
+        Ok(())
     }
     fn close_window(&mut self, win: &mut Window) {
         // println!("found texture for window");
         //destroy the texture
         //remove from window_buffers
-        self.window_buffers.remove(win.id.as_str());
+        let tex = self.window_buffers.remove(win.id.as_str());
+        self.back_buffers.remove(win.id.as_str());
+        self.animations.remove(win.id.as_str());
+        self.restore_bounds.remove(win.id.as_str());
         if let Some(n) = self.window_order.iter().position(|id|id == &win.id) {
             self.window_order.remove(n);
         }
+        //plain windows keep their last contents while they fade out, unless already out of sight
+        let duration = self.settings.effects.close;
+        if let Some(tex) = tex {
+            if win.window_type == WINDOW_TYPE_PLAIN && !win.minimized && duration > Duration::from_secs(0) {
+                self.closing.push(ClosingWindow { win: win.clone(), tex, animation: Animation::new(AnimationKind::Close, duration) });
+            }
+        }
     }
     pub fn start_loop(&mut self,
                       windows: &mut HashMap<String, Window>,
-                      input: &Receiver<RenderMessage>,
-                      output: &Sender<OwnedMessage>
-        ) -> Result<(),String> {
-        println!("sdl2 backend");
+        ) -> Result<LoopExit,String> {
+        debug!("sdl2 backend");
 
         let mut event_pump = self.sdl_context.event_pump()?;
 
         'done:loop {
             for event in event_pump.poll_iter() {
                 match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => {
-                        println!("quitting");
+                    Event::Quit { .. } => {
+                        info!("quitting");
+                        break 'done;
+                    },
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.quit_key => {
+                        info!("quitting");
                         break 'done;
                     },
-                    Event::KeyDown {keycode,keymod,..} => self.process_keydown(keycode, keymod, windows,output),
-                    Event::MouseButtonDown { x, y,mouse_btn, .. } => self.process_mousedown(x,y,mouse_btn, windows, output),
-                    Event::MouseButtonUp {x,y,mouse_btn,..} =>  self.process_mouseup(x,y,mouse_btn,windows,output),
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.debug_key => self.show_debug = !self.show_debug,
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.opacity_down_key => self.step_opacity(windows, -OPACITY_STEP),
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.opacity_up_key => self.step_opacity(windows, OPACITY_STEP),
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.minimize_key => self.minimize_focused(windows),
+                    Event::KeyDown {keycode: Some(key), ..} if key == self.settings.maximize_key => {
+                        if let Some(id) = self.active_window.clone() {
+                            self.toggle_maximize(windows, &id);
+                        }
+                    }
+                    Event::KeyDown {keycode,keymod,..} => self.process_keydown(keycode, keymod, windows),
+                    Event::Window { win_event: WindowEvent::SizeChanged(..), .. } => self.check_screen_size(windows),
+                    Event::MouseButtonDown { x, y,mouse_btn, .. } => self.process_mousedown(x,y,mouse_btn, windows),
+                    Event::MouseButtonUp {x,y,mouse_btn,..} =>  self.process_mouseup(x,y,mouse_btn,windows),
                     _ => {}
                 }
             }
             self.process_mousedrag(&event_pump.mouse_state(), windows);
+            //a display mode change doesn't always come with a window event
+            self.check_screen_size(windows);
+            self.check_theme();
 
-            self.process_render_messages(windows,
-                                         input,
-                                         output,
-            );
+            if let Processed::AllGone(reason) = self.process_render_messages(windows) {
+                debug!("{}", reason);
+                return Ok(LoopExit::Disconnected(reason));
+            }
             self.draw_windows(windows);
+            if self.show_debug {
+                self.draw_debug_overlay(windows);
+            }
             self.draw_cursor(&event_pump.mouse_state());
             self.canvas.present();
             ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
         }
-        println!("SDL thread is ending");
+        debug!("SDL thread is ending");
 
-        Ok(())
+        Ok(LoopExit::Quit)
     }
 
-    fn draw_windows(&mut self, windows: &mut HashMap<String, Window>) {
-        self.canvas.set_draw_color(Color::RGBA(255,0,255,255));
-        self.canvas.clear();
-        //clear background to white
+    fn check_screen_size(&mut self, windows: &mut HashMap<String, Window>) {
+        if let Ok(real) = self.canvas.output_size() {
+            if real != self.screen_size {
+                self.refresh_monitors();
+                self.set_screen_size(windows, real);
+            }
+        }
+    }
+    //a spanning screen changes size when a display is added, removed or moved, so the monitors
+    //are read again then. sdl2 0.34 has no display events, so a display changing without the
+    //spanning window changing size isn't noticed, and neither is a single display becoming several
+    fn refresh_monitors(&mut self) {
+        if self.monitors.is_empty() {
+            return;
+        }
+        let displays = self.sdl_context.video().and_then(|video| {
+            let count = video.num_video_displays()?;
+            (0..count).map(|n| video.display_bounds(n)).collect::<Result<Vec<_>, _>>()
+        });
+        match displays {
+            Ok(displays) => if let Some(all) = span(&displays) {
+                self.monitors = monitor_bounds(&displays, (all.x(), all.y()), self.scale);
+            },
+            Err(err) => info!("could not read the displays again: {}", err),
+        }
+    }
+    //the host window or display changed size. every server hears the new virtual size, and
+    //windows that would end up off the edge are moved back on
+    pub fn set_screen_size(&mut self, windows: &mut HashMap<String, Window>, real: (u32, u32)) {
+        self.screen_size = real;
+        let (width, height) = virtual_size(real, self.scale);
+        debug!("screen is now {}x{}", width, height);
+        let size_msg = SetScreenSize {
+            type_: SetScreenSize_message.to_string(),
+            width: width as i64,
+            height: height as i64,
+        };
+        for connection in 0..self.servers.len() {
+            if self.servers[connection].open {
+                self.send_to_server(connection, SetScreenSize_message, &size_msg);
+            }
+        }
+        let screen = Bounds { x: 0, y: 0, width: width as i32, height: height as i32 };
+        for win in windows.values_mut() {
+            //plain windows keep their frame on the screen too
+            let outer = if win.window_type == WINDOW_TYPE_PLAIN { self.theme.outer(win) } else { win.bounds() };
+            let inside = outer.keep_inside(&screen);
+            if inside.x == outer.x && inside.y == outer.y { continue; }
+            win.x += inside.x - outer.x;
+            win.y += inside.y - outer.y;
+            let move_msg = WindowSetPosition {
+                type_: WindowSetPosition_message.to_string(),
+                app: String::from("rust-client"),
+                window: win.remote_id().to_string(),
+                x: win.x as i64,
+                y: win.y as i64,
+            };
+            self.send_to_server(win.connection, WindowSetPosition_message, &move_msg);
+        }
+    }
+    fn monitor_areas(&self) -> Vec<Bounds> {
+        if !self.monitors.is_empty() {
+            return self.monitors.clone();
+        }
+        let screen = self.virtual_size();
+        vec![Bounds { x: 0, y: 0, width: screen.width, height: screen.height }]
+    }
+    //fill the monitor the window is mostly on, or put it back how it was
+    pub fn toggle_maximize(&mut self, windows: &mut HashMap<String, Window>, id: &str) {
+        let bounds = match windows.get(id) {
+            Some(win) if win.window_type == WINDOW_TYPE_PLAIN => win.bounds(),
+            _ => return,
+        };
+        let target = match self.restore_bounds.remove(id) {
+            Some(old) => old,
+            None => {
+                let center = Point { x: bounds.x + bounds.width / 2, y: bounds.y + bounds.height / 2 };
+                let monitor = match monitor_at(&self.monitor_areas(), &center) {
+                    Some(monitor) => monitor,
+                    None => return,
+                };
+                self.restore_bounds.insert(id.to_string(), bounds);
+                fill(&monitor, &self.theme.frame())
+            }
+        };
+        self.place_window(windows, id, target);
+    }
+    //move and resize a window to bounds on the screen, and tell its app
+    fn place_window(&mut self, windows: &mut HashMap<String, Window>, id: &str, target: Bounds) {
+        let win = match windows.get_mut(id) {
+            Some(win) => win,
+            None => return,
+        };
+        //the app's size is in its own pixels
+        let scale = win.scale.max(1) as i32;
+        win.x = target.x;
+        win.y = target.y;
+        win.width = (target.width / scale).max(1);
+        win.height = (target.height / scale).max(1);
+        let resized = self.resize_window(win);
+        let move_msg = WindowSetPosition {
+            type_: WindowSetPosition_message.to_string(),
+            app: String::from("rust-client"),
+            window: win.remote_id().to_string(),
+            x: win.x as i64,
+            y: win.y as i64,
+        };
+        self.send_to_server(win.connection, WindowSetPosition_message, &move_msg);
+        let size_msg = WindowSetSize {
+            type_: WindowSetSize_message.to_string(),
+            app: String::from("rust-client"),
+            window: win.remote_id().to_string(),
+            width: win.width as i64,
+            height: win.height as i64,
+        };
+        self.send_to_server(win.connection, WindowSetSize_message, &size_msg);
+        if let Err(err) = resized {
+            self.report_error(windows, Some(id.to_string()), &err);
+        }
+    }
+    //never all the way to invisible, or the window could be lost
+    fn step_opacity(&mut self, windows: &mut HashMap<String, Window>, step: i32) {
+        if let Some(win) = self.active_window.as_ref().and_then(|id| windows.get_mut(id)) {
+            win.opacity = (win.opacity as i32 + step).max(OPACITY_STEP).min(255) as u8;
+        }
+    }
+    pub fn draw_windows(&mut self, windows: &mut HashMap<String, Window>) {
+        self.draw_windows_at(windows, Instant::now());
+    }
+    //a frame as it would look at now, which is when animations are measured from
+    pub fn draw_windows_at(&mut self, windows: &mut HashMap<String, Window>, now: Instant) {
+        self.draw_desktop();
+        //everything else is drawn in virtual pixels, and SDL scales it. it only applies to
+        //the screen, drawing into window textures is always 1:1
+        let _ = self.canvas.set_scale(self.scale, self.scale);
+        //so translucent frames show what is beneath them
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        let screen = self.virtual_size();
         //for each window
         for id in self.window_order.iter() {
             if let Some(win) = windows.get(id) {
-                if let Some(tex) = self.window_buffers.get(id) {
-                    //draw background / border
-                    // println!("drawing window type {:?}",win.window_type);
-                    match win.window_type.as_str() {
-                        "MENUBAR" => {}
-                        "DOCK" => {}
-                        "SIDEBAR" => {}
-                        "CHILD" => {}
-                        "PLAIN" => {
-                            // self.canvas.set_draw_color(self.calc_window_border_color(win));
-                            self.canvas.set_draw_color(Color::RED);
-                            self.canvas.fill_rect(Rect::new(
-                                ((win.x-BORDER.left)*(self.scale as i32)) as i32,
-                                ((win.y-BORDER.top)*(self.scale as i32)) as i32,
-                                (BORDER.left+win.width+BORDER.right)as u32*self.scale,
-                                (BORDER.top+win.height+BORDER.bottom)as u32*self.scale));
-                            draw_title(&mut self.canvas, &self.font_info, &win, self.scale)
-                        }
-                        _ => {
-                            println!("unknown window type {:?}",win.window_type);
-                        }
+                let animation = self.animations.get(id);
+                //minimized windows are only seen on their way down
+                if win.minimized && animation.is_none() { continue; }
+                let shown;
+                let win = match animation {
+                    Some(animation) => {
+                        shown = animation.frame(win, now, screen);
+                        &shown
                     }
-                    //draw window texture
-                    let dst = Some(Rect::new((win.x * self.iscale) as i32,
-                                             (win.y * self.iscale) as i32,
-                                             (win.width as u32 * self.iscale as u32) as u32,
-                                             (win.height as u32 * self.iscale as u32) as u32
-                    ));
-                    self.canvas.copy(tex,None,dst);
+                    None => win,
+                };
+                if let Some(tex) = self.window_buffers.get_mut(id) {
+                    let active = self.active_window.as_ref() == Some(&win.id);
+                    //with several servers, say which one the window came from
+                    let origin = if self.servers.len() > 1 {
+                        self.servers.get(win.connection).map(|s| s.label.as_str())
+                    } else {
+                        None
+                    };
+                    let font = self.theme.title_font.as_ref().unwrap_or(&self.font_info);
+                    draw_window(&mut self.canvas, &self.theme, font, &self.settings.effects, win, tex, active, origin);
                 }
             }
         }
+        //closed windows fade out on top of the rest
+        for closing in self.closing.iter_mut() {
+            let shown = closing.animation.frame(&closing.win, now, screen);
+            let font = self.theme.title_font.as_ref().unwrap_or(&self.font_info);
+            draw_window(&mut self.canvas, &self.theme, font, &self.settings.effects, &shown, &mut closing.tex, false, None);
+        }
+        self.closing.retain(|closing| !closing.animation.done(now));
+        self.animations.retain(|_, animation| !animation.done(now));
         // self.font.draw_text_at("idealos", 150,0,&Color::GREEN, &mut self.canvas, SCALEI);
     }
-    fn process_keydown(&self, keycode: Option<Keycode>,  keymod:Mod, windows:&mut HashMap<String,Window>, output: &Sender<OwnedMessage>) {
+    //the screen in virtual pixels
+    fn virtual_size(&self) -> Dimensions {
+        let (width, height) = virtual_size(self.canvas.output_size().unwrap_or((0, 0)), self.scale);
+        Dimensions { width: width as i32, height: height as i32 }
+    }
+    //a point from an input event, in virtual pixels. SDL gives these in host window
+    //coordinates, which aren't always the renderer's pixels
+    fn virtual_point(&self, x: i32, y: i32) -> Point {
+        let (real_w, real_h) = self.canvas.output_size().unwrap_or((1, 1));
+        let (win_w, win_h) = self.canvas.window().size();
+        let ratio_x = if win_w > 0 { real_w as f32 / win_w as f32 } else { 1.0 };
+        let ratio_y = if win_h > 0 { real_h as f32 / win_h as f32 } else { 1.0 };
+        Point { x: to_virtual(x as f32 * ratio_x, self.scale), y: to_virtual(y as f32 * ratio_y, self.scale) }
+    }
+    //start an animation, replacing any the window had. a zero duration just ends it
+    fn animate(&mut self, id: &str, kind: AnimationKind, duration: Duration) {
+        if duration > Duration::from_secs(0) {
+            self.animations.insert(id.to_string(), Animation::new(kind, duration));
+        } else {
+            self.animations.remove(id);
+        }
+    }
+    fn set_minimized(&mut self, windows: &mut HashMap<String, Window>, id: &str, minimized: bool) {
+        if let Some(win) = windows.get_mut(id) {
+            if win.minimized == minimized { return; }
+            win.minimized = minimized;
+            let kind = if minimized { AnimationKind::Minimize } else { AnimationKind::Restore };
+            self.animate(id, kind, self.settings.effects.minimize);
+            if minimized {
+                if self.active_window.as_deref() == Some(id) { self.active_window = None; }
+                if self.dragtarget.as_deref() == Some(id) {
+                    self.dragtarget = None;
+                    self.dragging = false;
+                    self.resizing = false;
+                }
+            }
+        }
+    }
+    //from the minimize key. the app is told, so a dock can offer to restore the window
+    fn minimize_focused(&mut self, windows: &mut HashMap<String, Window>) {
+        if let Some(id) = self.active_window.clone() {
+            self.set_minimized(windows, &id, true);
+            if let Some(win) = windows.get(&id) {
+                let msg = WindowSetMinimized {
+                    type_: WindowSetMinimized_message.to_string(),
+                    window: win.remote_id().to_string(),
+                    minimized: true,
+                };
+                self.send_to_server(win.connection, WindowSetMinimized_message, &msg);
+            }
+        }
+    }
+    //laid out against the current output size every frame, so it follows size and scale changes
+    //in real pixels, so a big wallpaper keeps its detail
+    fn draw_desktop(&mut self) {
+        let _ = self.canvas.set_scale(1.0, 1.0);
+        self.canvas.set_draw_color(self.desktop.color);
+        self.canvas.clear();
+        if let Some(image) = &self.desktop.image {
+            let query = image.query();
+            let screen = self.canvas.output_size().unwrap_or((0, 0));
+            for dst in wallpaper_rects(self.desktop.mode, (query.width, query.height), screen, self.scale) {
+                let _ = self.canvas.copy(image, None, Some(dst));
+            }
+        }
+    }
+    //swap in a new theme, which replaces any wallpaper an app set.
+    //the old theme stays if the wallpaper can't be made
+    pub fn set_theme(&mut self, theme:Theme) -> ClientResult<()> {
+        let image = match &theme.background_image {
+            Some(img) => Some(self.image_texture(img)?),
+            None => None,
+        };
+        self.desktop = Desktop { color: theme.background, image, mode: theme.wallpaper_mode };
+        self.theme = theme;
+        Ok(())
+    }
+    fn set_wallpaper(&mut self, update:WallpaperUpdate) -> ClientResult<()> {
+        //check everything before changing anything, so a bad message leaves the desktop alone
+        let color = match &update.color {
+            Some(color) => Some(lookup_color(color)?),
+            None => None,
+        };
+        let image = match &update.image {
+            Some(Some(img)) => Some(Some(self.image_texture(img)?)),
+            Some(None) => Some(None),
+            None => None,
+        };
+        if let Some(color) = color { self.desktop.color = color; }
+        if let Some(image) = image { self.desktop.image = image; }
+        if let Some(mode) = update.mode { self.desktop.mode = mode; }
+        Ok(())
+    }
+    fn check_theme(&mut self) {
+        let reloaded = match &mut self.theme_watcher {
+            Some(watcher) => watcher.poll(),
+            None => None,
+        };
+        match reloaded {
+            Some(Ok(theme)) => match self.set_theme(theme) {
+                Ok(()) => info!("reloaded the theme"),
+                Err(e) => info!("could not use the new theme: {}", e),
+            },
+            Some(Err(e)) => info!("keeping the old theme: {}", e),
+            None => {}
+        }
+    }
+    fn image_texture(&self, img:&RgbaImage) -> ClientResult<Texture<'a>> {
+        self.rgba_texture(img.width(), img.height(), img.as_raw())
+    }
+    //a blending texture from RGBA bytes, the layout of both DrawImage and the image crate
+    fn rgba_texture(&self, width: u32, height: u32, pixels: &[u8]) -> ClientResult<Texture<'a>> {
+        //ABGR8888 is RGBA byte order on little endian machines
+        let mut tex = self.creator.create_texture_static(PixelFormatEnum::ABGR8888, width, height)
+            .map_err(|e|ClientError::Texture(e.to_string()))?;
+        tex.update(None, pixels, width as usize * 4)
+            .map_err(|e|ClientError::Texture(e.to_string()))?;
+        tex.set_blend_mode(BlendMode::Blend);
+        Ok(tex)
+    }
+    fn process_keydown(&self, keycode: Option<Keycode>,  keymod:Mod, windows:&mut HashMap<String,Window>) {
         if let Some(keycode) = keycode {
             match keycode {
                 Keycode => {
                     if let Some(id) = &self.active_window {
                         if let Some(win) = windows.get_mut(id) {
-                            println!("got a message {:?}",keycode.name());
-                            println!("mod is {:?}",keymod);
+                            debug!("got a message {:?}",keycode.name());
+                            debug!("mod is {:?}",keymod);
                             let mut key = keycode.name().to_lowercase();
                             let mut name = keycode.name();
                             let shift = (keymod == Mod::LSHIFTMOD || keymod == Mod::RSHIFTMOD);
@@ -374,7 +995,7 @@ impl<'a> SDL2Backend<'a> {
                                 code = "Space".to_string();
                                 key = " ".to_string();
                             }
-                            println!("code is {} key is {}",code, key);
+                            debug!("code is {} key is {}",code, key);
                             //keycode.name is Left
                             let msg = KeyboardDown {
                                 type_: KeyboardDown_name.to_string(),
@@ -386,9 +1007,9 @@ impl<'a> SDL2Backend<'a> {
                                 control,
                                 app:win.owner.to_string(),
                                 target: win.owner.clone(),
-                                window: win.id.to_string()
+                                window: win.remote_id().to_string()
                             };
-                            output.send(OwnedMessage::Text(json!(msg).to_string()));
+                            self.send_to_server(win.connection, KeyboardDown_name, &msg);
                         }
                     }
                 }
@@ -398,12 +1019,22 @@ impl<'a> SDL2Backend<'a> {
         }
 
     }
-    fn process_mousedown(&mut self, x: i32, y: i32, mouse_btn: MouseButton, windows: &mut HashMap<String, Window>, output: &Sender<OwnedMessage>) {
+    pub fn process_mousedown(&mut self, x: i32, y: i32, mouse_btn: MouseButton, windows: &mut HashMap<String, Window>) {
         match mouse_btn {
             MouseButton::Left => {
-                let pt = Point { x: x / self.iscale, y: y / self.iscale, };
+                let pt = self.virtual_point(x, y);
                 for win in windows.values() {
-                    if win.resize_contains(&pt, &RESIZE) {
+                    if win.minimized { continue; }
+                    if win.window_type.eq("PLAIN") && self.theme.close_button(win).map_or(false, |b| b.contains(&pt)) {
+                        let msg = WindowCloseRequest {
+                            type_: WindowCloseRequest_message.to_string(),
+                            target: win.owner.clone(),
+                            window: win.remote_id().to_string(),
+                        };
+                        self.send_to_server(win.connection, WindowCloseRequest_message, &msg);
+                        break;
+                    }
+                    if win.resize_contains(&pt, &self.theme.resize) {
                         self.resizing = true;
                         self.dragtarget = Some(win.id.clone());
                         break;
@@ -412,23 +1043,24 @@ impl<'a> SDL2Backend<'a> {
                         if win.window_type.eq("PLAIN") {
                             self.active_window = Some(win.id.clone());
                             let window_focus_msg = set_focused_window_message {
-                                type_: "MAKE_SetFocusedWindow_name".to_string(),
-                                window: win.id.to_string()
+                                type_: SetFocusedWindow_name.to_string(),
+                                window: win.remote_id().to_string()
                             };
-                            output.send(OwnedMessage::Text(json!(window_focus_msg).to_string()));
+                            self.send_to_server(win.connection, SetFocusedWindow_name, &window_focus_msg);
                             self.raise_window(win);
                         }
+                        let local = win.to_local(&pt);
                         let msg = MouseDown {
                             type_:MouseDown_name.to_string(),
-                            x: ((pt.x) - win.x) as i64,
-                            y: ((pt.y) - win.y) as i64,
+                            x: local.x as i64,
+                            y: local.y as i64,
                             target: win.owner.clone(),
-                            window: win.id.to_string(),
+                            window: win.remote_id().to_string(),
                         };
-                        output.send(OwnedMessage::Text(json!(msg).to_string()));
+                        self.send_to_server(win.connection, MouseDown_name, &msg);
                         continue;
                     }
-                    if win.border_contains(&pt, &BORDER) {
+                    if win.border_contains(&pt, &self.theme.frame()) {
                         // println!("clicked on the border");
                         self.dragging = true;
                         self.dragtarget = Some(win.id.clone());
@@ -439,85 +1071,97 @@ impl<'a> SDL2Backend<'a> {
         };
 
     }
-    fn process_mouseup(&mut self, x: i32, y: i32, mouse_btn: MouseButton, windows: &mut HashMap<String, Window>, output: &Sender<OwnedMessage>) {
+    pub fn process_mouseup(&mut self, x: i32, y: i32, mouse_btn: MouseButton, windows: &mut HashMap<String, Window>) {
         if self.dragging {
-            if let Some(winid) = &self.dragtarget {
-                if let Some(win) = windows.get(winid) {
-                    let pt = Point { x: x / self.iscale, y: y / self.iscale, };
-                    let move_msg = WindowSetPosition {
-                        type_: WindowSetPosition_message.to_string(),
-                        app: String::from("someappid"),
-                        window: winid.to_string(),
-                        x: pt.x as i64,
-                        y: pt.y as i64,
-                    };
-                    // println!("setting window position {:?}",move_msg);
-                    output.send(OwnedMessage::Text(json!(move_msg).to_string()));
+            if let Some(winid) = self.dragtarget.clone() {
+                let pt = self.virtual_point(x, y);
+                //let go at the edge of a monitor, the window snaps to part of it
+                let snap = monitor_at(&self.monitor_areas(), &pt).and_then(|monitor| snap_area(&monitor, &pt));
+                match (snap, windows.get(&winid)) {
+                    (Some(area), Some(win)) => {
+                        let bounds = win.bounds();
+                        self.restore_bounds.entry(winid.clone()).or_insert(bounds);
+                        let target = fill(&area, &self.theme.frame());
+                        self.place_window(windows, &winid, target);
+                    }
+                    (None, Some(win)) => {
+                        //moved somewhere ordinary, so it is no longer maximized
+                        self.restore_bounds.remove(&winid);
+                        let move_msg = WindowSetPosition {
+                            type_: WindowSetPosition_message.to_string(),
+                            app: String::from("someappid"),
+                            window: win.remote_id().to_string(),
+                            x: pt.x as i64,
+                            y: pt.y as i64,
+                        };
+                        // println!("setting window position {:?}",move_msg);
+                        self.send_to_server(win.connection, WindowSetPosition_message, &move_msg);
+                    }
+                    _ => {}
                 }
             }
             self.dragging = false;
         }
 
         if self.resizing {
-            if let Some(winid) = &self.dragtarget {
-                if let Some(win) = windows.get(winid) {
-                    let edge = Point { x: x / self.iscale, y: y / self.iscale };
-                    let pos = Point { x: win.x, y: win.y };
+            if let Some(winid) = self.dragtarget.clone() {
+                if let Some(win) = windows.get(&winid) {
+                    //the app's size, not the magnified one on the screen
+                    let edge = win.to_local(&self.virtual_point(x, y));
                     let size_msg = WindowSetSize {
                         type_: WindowSetSize_message.to_string(),
                         app: String::from("someappid"),
-                        window: winid.to_string(),
-                        width: (edge.x - pos.x) as i64,
-                        height: (edge.y - pos.y)as i64,
+                        window: win.remote_id().to_string(),
+                        width: edge.x as i64,
+                        height: edge.y as i64,
                     };
-                    output.send(OwnedMessage::Text(json!(size_msg).to_string()));
+                    self.send_to_server(win.connection, WindowSetSize_message, &size_msg);
 
-                    self.resize_window(win);
+                    if let Err(err) = self.resize_window(win) {
+                        self.report_error(windows, Some(winid.clone()), &err);
+                    }
                 }
             }
             self.resizing = false;
         }
 
         if let MouseButton::Left = mouse_btn {
-            let pt = Point { x: x / self.iscale, y: y/ self.iscale };
+            let pt = self.virtual_point(x, y);
             for win in windows.values() {
+                if win.minimized { continue; }
                 if win.contains(&pt) {
+                    let local = win.to_local(&pt);
                     let msg = MouseUp {
                         type_: MouseUp_name.to_string(),
-                        x: ((pt.x) - win.x) as i64,
-                        y: ((pt.y) - win.y) as i64,
+                        x: local.x as i64,
+                        y: local.y as i64,
                         target: win.owner.clone(),
-                        window: win.id.to_string(),
+                        window: win.remote_id().to_string(),
                     };
-                    output.send(OwnedMessage::Text(json!(msg).to_string()));
+                    self.send_to_server(win.connection, MouseUp_name, &msg);
                     break;
                 }
             }
         }
 
     }
-    fn calc_window_border_color(&self, win: &Window) -> Color {
-        return if self.active_window == Some(win.id.clone()) {
-            Color::RGBA(0, 255, 255, 255)
-        } else {
-            Color::RGBA(255, 255, 0, 255)
-        }
-    }
     fn process_mousedrag(&self, mouse_state:&MouseState, windows:&mut HashMap<String,Window>) -> () {
+        let pt = self.virtual_point(mouse_state.x(), mouse_state.y());
         if self.dragging {
             if let Some(winid) = &self.dragtarget {
                 if let Some(win) = windows.get_mut(winid) {
                     // println!("dragging {} {} with {:?}", mouse_state.x(), mouse_state.y(), win.id);
-                    win.x = mouse_state.x()/self.iscale;
-                    win.y = mouse_state.y()/self.iscale;
+                    win.x = pt.x;
+                    win.y = pt.y;
                 }
             }
         }
         if self.resizing {
             if let Some(winid) = &self.dragtarget {
                 if let Some(win) = windows.get_mut(winid) {
-                    win.width = (mouse_state.x()/self.iscale) - win.x;
-                    win.height = (mouse_state.y()/self.iscale) - win.y;
+                    let edge = win.to_local(&pt);
+                    win.width = edge.x;
+                    win.height = edge.y;
                 }
             }
         }
@@ -529,54 +1173,130 @@ impl<'a> SDL2Backend<'a> {
             self.window_order.push(id)
         }
     }
+    fn draw_debug_overlay(&mut self, windows: &HashMap<String, Window>) {
+        let pings:Vec<String> = self.servers.iter().map(|server| {
+            match (server.open, server.heartbeat.latency()) {
+                (false, _) => "down".to_string(),
+                (true, Some(lat)) => format!("{}ms", lat.as_millis()),
+                (true, None) => "-".to_string(),
+            }
+        }).collect();
+        let text = format!("ping {} windows {}", pings.join(" "), windows.len());
+        self.canvas.set_draw_color(Color::WHITE);
+        self.canvas.fill_rect(Rect::new(0, 0, text.len() as u32 * 8 + 4, 12));
+        draw_text(&mut self.canvas, &self.font_info, &text, 2, 0, Color::BLACK);
+    }
     fn draw_cursor(&mut self, mouse: &MouseState) {
-        if let Some(cursor_glyph) = lookup_char(&self.font_info, 1) {
-            draw_glyph(&mut self.canvas, cursor_glyph, mouse.x()/self.iscale, mouse.y()/self.iscale, self.scale);
+        if let Some(cursor_glyph) = lookup_char(&self.font_info, self.theme.cursor_glyph) {
+            let pt = self.virtual_point(mouse.x(), mouse.y());
+            draw_glyph(&mut self.canvas, cursor_glyph, pt.x, pt.y, self.theme.cursor_color);
+        }
+    }
+}
+
+//translucent fills are blended over what is already in the window. opaque ones just replace it
+fn fill_blend(color: Color) -> BlendMode {
+    if color.a == 255 { BlendMode::None } else { BlendMode::Blend }
+}
+
+//the texture drawing messages should go to: the back buffer if the window is double buffered, otherwise the front
+fn draw_target<'t, 'a>(back: &'t mut HashMap<String, Texture<'a>>, front: &'t mut HashMap<String, Texture<'a>>, id: &str) -> Option<&'t mut Texture<'a>> {
+    match back.get_mut(id) {
+        Some(tex) => Some(tex),
+        None => front.get_mut(id),
+    }
+}
+
+//the border and title bar around a window, in the theme's geometry
+pub fn draw_frame(canvas:&mut WindowCanvas, theme:&Theme, font:&FontInfo2, win:&Window, active:bool, origin:Option<&str>, opacity:u8) {
+    let outer = theme.outer(win);
+    let border = if active { theme.active_border } else { theme.inactive_border };
+    fill_bounds(canvas, fade(border, opacity), &outer);
+    let title_bar = theme.title_bar(win);
+    let title = if active { theme.active_title } else { theme.inactive_title };
+    fill_bounds(canvas, fade(title, opacity), &title_bar);
+    let text = fade(theme.title_text, opacity);
+    draw_title(canvas, font, win, origin, theme.title_height, text);
+    if let (Some(glyph), Some(button)) = (theme.close_glyph, theme.close_button(win)) {
+        if let Some(glyph) = lookup_char(font, glyph) {
+            draw_glyph(canvas, glyph, button.x - glyph.left, button.y, text);
         }
     }
 }
 
-fn lookup_color(name: &String) -> Color {
-    if name.starts_with("#") {
-        // println!("its hex");
-        let col = Rgb::from_hex_str(name).unwrap();
-        // println!("parsed the color ${:?}",col);
-        return Color::RGBA(col.get_red() as u8, col.get_green() as u8, col.get_blue() as u8, 255);
-    }
-    return match name.as_str() {
-        "red" => Color::RED,
-        "black" => Color::BLACK,
-        "blue" => Color::BLUE,
-        "white" => Color::WHITE,
-        "green" => Color::GREEN,
-        "yellow" => Color::YELLOW,
-        "grey" => Color::GREY,
-        "gray" => Color::GRAY,
-        "magenta" => Color::MAGENTA,
-        "teal" => Color::RGB(0,128,128),
-        "aqua" => Color::RGB(0,255,255),
-        "cyan" => Color::CYAN,
+//a soft shadow, spread over the theme's shadow size
+pub fn draw_shadow(canvas:&mut WindowCanvas, theme:&Theme, win:&Window) {
+    if theme.shadow_size == 0 { return; }
+    let c = theme.shadow_color;
+    let layer = fade(Color::RGBA(c.r, c.g, c.b, (c.a as i32 / theme.shadow_size) as u8), win.opacity);
+    if layer.a == 0 { return; }
+    for b in shadow_layers(theme.outer(win), theme.shadow_size) {
+        fill_bounds(canvas, layer, &b);
+    }
+}
+
+//a window with its frame and effects, at the window's own bounds and opacity
+fn draw_window(canvas:&mut WindowCanvas, theme:&Theme, font:&FontInfo2, effects:&Effects, win:&Window, tex:&mut Texture, active:bool, origin:Option<&str>) {
+    let plain = win.window_type == WINDOW_TYPE_PLAIN;
+    //draw background / border
+    // println!("drawing window type {:?}",win.window_type);
+    match win.window_type.as_str() {
+        "MENUBAR" => {}
+        "DOCK" => {}
+        "SIDEBAR" => {}
+        "CHILD" => {}
+        "PLAIN" => {
+            if effects.shadows {
+                draw_shadow(canvas, theme, win);
+            }
+            draw_frame(canvas, theme, font, win, active, origin, win.opacity);
+        }
         _ => {
-            println!("unknown color {}",name);
-            Color::MAGENTA
-        },
+            debug!("unknown window type {:?}",win.window_type);
+        }
+    }
+    //draw window texture
+    tex.set_alpha_mod(win.opacity);
+    let bounds = win.bounds();
+    if bounds.width <= 0 || bounds.height <= 0 { return; }
+    let dst = Some(Rect::new(bounds.x, bounds.y, bounds.width as u32, bounds.height as u32));
+    let _ = canvas.copy(tex, None, dst);
+    if plain && !active && effects.dim_inactive {
+        fill_bounds(canvas, fade(theme.dim, win.opacity), &bounds);
+    }
+}
+
+//the color with its alpha scaled by the opacity
+pub fn fade(color:Color, opacity:u8) -> Color {
+    Color::RGBA(color.r, color.g, color.b, (color.a as u16 * opacity as u16 / 255) as u8)
+}
+
+fn fill_bounds(canvas:&mut WindowCanvas, color:Color, b:&Bounds) {
+    if b.width <= 0 || b.height <= 0 { return; }
+    canvas.set_draw_color(color);
+    let _ = canvas.fill_rect(Rect::new(b.x, b.y, b.width as u32, b.height as u32));
+}
+
+pub fn draw_title(canvas:&mut WindowCanvas, font:&FontInfo2, win:&Window, origin:Option<&str>, title_height:i32, color:Color) {
+    match origin {
+        Some(origin) => draw_text(canvas, font, &format!("[{}] {}", origin, win.title), win.x, win.y-title_height, color),
+        None => draw_text(canvas, font, &win.title, win.x, win.y-title_height, color),
     }
 }
 
-pub fn draw_title(canvas:&mut WindowCanvas, font:&FontInfo2, win:&Window, scale:u32) {
+pub fn draw_text(canvas:&mut WindowCanvas, font:&FontInfo2, text:&str, x:i32, y:i32, color:Color) {
     let mut ww:i32 = 0;
-    for ch in win.title.bytes() {
+    for ch in text.bytes() {
         let glyph_opt = lookup_char(font,ch);
         if let Some(glyph) = glyph_opt {
-            canvas.set_draw_color(Color::RED);
-            draw_glyph(canvas,glyph,win.x- glyph.left +ww,win.y-BORDER.top, scale);
+            draw_glyph(canvas,glyph,x- glyph.left +ww,y, color);
             ww += (glyph.width - glyph.left - glyph.right) as i32;
             ww += 1;
         }
     }
 }
 
-pub fn draw_glyph(canvas:&mut WindowCanvas, glyph: &GlyphInfo, x: i32, y: i32, scale:u32) {
+pub fn draw_glyph(canvas:&mut WindowCanvas, glyph: &GlyphInfo, x: i32, y: i32, color:Color) {
     let w:i32 = glyph.width as i32;
     let h:i32 = glyph.height as i32;
     let f = 1;
@@ -585,11 +1305,11 @@ pub fn draw_glyph(canvas:&mut WindowCanvas, glyph: &GlyphInfo, x: i32, y: i32, s
             let n:usize = (j * w + i) as usize;
             let alpha = glyph.data[n];
             if alpha > 0 {
-                canvas.set_draw_color(Color::BLACK);
+                canvas.set_draw_color(color);
                 canvas.fill_rect(Rect::new(
-                    (i + x)*scale as i32,
-                    (y + j + f)*scale as i32,
-                    scale, scale
+                    i + x,
+                    y + j + f,
+                    1, 1
                 ));
             }
         }
@@ -0,0 +1,302 @@
+use std::fs::{metadata, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use image::RgbaImage;
+use serde::{Serialize, Deserialize};
+use sdl2::pixels::Color;
+use crate::desktop::WallpaperMode;
+use crate::font::{FontInfo2, load_font2};
+use crate::color::lookup_color;
+use crate::window::{Window, Insets, Dimensions, Bounds};
+use crate::config::{DecorationConfig, ColorConfig};
+
+//how often the theme file is checked for changes
+const RELOAD_INTERVAL:Duration = Duration::from_secs(1);
+
+//a theme file, in TOML. every field has a default, so a theme only needs what it changes.
+//sizes are in virtual pixels, colors are anything a draw message could use.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub border:BorderConfig,
+    pub title:TitleConfig,
+    pub desktop:DesktopConfig,
+    pub cursor:CursorConfig,
+    pub effects:EffectsLook,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BorderConfig {
+    pub left:i32,
+    pub right:i32,
+    //above the title bar
+    pub top:i32,
+    pub bottom:i32,
+    //the square in the bottom right corner of a window that resizes it
+    pub resize_handle:i32,
+    pub active:String,
+    pub inactive:String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitleConfig {
+    pub height:i32,
+    pub active:String,
+    pub inactive:String,
+    pub text:String,
+    //a font json file, like the one the screen uses. the screen font if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font:Option<PathBuf>,
+    //the glyph drawn as the close button at the right of the title bar. 0 for no button
+    pub close_glyph:u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DesktopConfig {
+    pub background:String,
+    //drawn over the background color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image:Option<PathBuf>,
+    //stretched, scaled, centered or tiled
+    pub mode:WallpaperMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CursorConfig {
+    pub glyph:u8,
+    pub color:String,
+}
+
+//how the effects look. whether they are drawn at all is up to the screen config
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectsLook {
+    //how far the shadow reaches past the bottom right of a window
+    pub shadow_size:i32,
+    //at the window's edge. it fades out from there
+    pub shadow_color:String,
+    //drawn over windows that don't have the focus
+    pub dim:String,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        BorderConfig {
+            left: 1, right: 1, top: 0, bottom: 1,
+            resize_handle: 10,
+            active: "red".to_string(),
+            inactive: "red".to_string(),
+        }
+    }
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        TitleConfig {
+            height: 10,
+            active: "red".to_string(),
+            inactive: "red".to_string(),
+            text: "black".to_string(),
+            font: None,
+            close_glyph: 0,
+        }
+    }
+}
+
+impl Default for DesktopConfig {
+    fn default() -> Self {
+        DesktopConfig { background: "magenta".to_string(), image: None, mode: WallpaperMode::Stretched }
+    }
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        CursorConfig { glyph: 1, color: "black".to_string() }
+    }
+}
+
+impl Default for EffectsLook {
+    fn default() -> Self {
+        EffectsLook {
+            shadow_size: 4,
+            shadow_color: "rgba(0, 0, 0, 0.5)".to_string(),
+            dim: "rgba(0, 0, 0, 0.2)".to_string(),
+        }
+    }
+}
+
+//a theme ready to draw with
+pub struct Theme {
+    pub border:Insets,
+    pub title_height:i32,
+    pub resize:Dimensions,
+    pub active_border:Color,
+    pub inactive_border:Color,
+    pub active_title:Color,
+    pub inactive_title:Color,
+    pub title_text:Color,
+    pub title_font:Option<FontInfo2>,
+    pub close_glyph:Option<u8>,
+    pub background:Color,
+    pub background_image:Option<RgbaImage>,
+    pub wallpaper_mode:WallpaperMode,
+    pub cursor_glyph:u8,
+    pub cursor_color:Color,
+    pub shadow_size:i32,
+    pub shadow_color:Color,
+    pub dim:Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        //the defaults don't touch the disk and are all valid
+        Theme::from_config(&ThemeConfig::default(), Path::new(".")).unwrap()
+    }
+}
+
+impl Theme {
+    pub fn load(path:&Path) -> Result<Theme, String> {
+        Theme::load_with(Some(path), &DecorationConfig::default(), &ColorConfig::default())
+    }
+    //the theme file, or the default theme without one, with the screen config's deprecated
+    //[decorations] and [colors] laid over it
+    pub fn load_with(path:Option<&Path>, decorations:&DecorationConfig, colors:&ColorConfig) -> Result<Theme, String> {
+        let mut config:ThemeConfig = match path {
+            Some(path) => {
+                let text = read_to_string(path).map_err(|e|format!("could not read theme {:?}: {}", path, e))?;
+                toml::from_str(&text).map_err(|e|format!("bad theme {:?}: {}", path, e))?
+            }
+            None => ThemeConfig::default(),
+        };
+        decorations.apply(&mut config);
+        colors.apply(&mut config);
+        match path {
+            //files the theme names are relative to the theme
+            Some(path) => Theme::from_config(&config, path.parent().unwrap_or_else(|| Path::new(".")))
+                .map_err(|e|format!("bad theme {:?}: {}", path, e)),
+            None => Theme::from_config(&config, Path::new(".")),
+        }
+    }
+    pub fn from_config(config:&ThemeConfig, dir:&Path) -> Result<Theme, String> {
+        let b = &config.border;
+        if b.left < 0 || b.right < 0 || b.top < 0 || b.bottom < 0 || b.resize_handle < 0 || config.title.height < 0
+            || config.effects.shadow_size < 0 {
+            return Err("border, title and shadow sizes can't be negative".to_string());
+        }
+        let title_font = match &config.title.font {
+            Some(path) => Some(load_font2(&dir.join(path).to_string_lossy())
+                .map_err(|e|format!("could not load title font {:?}: {}", path, e))?),
+            None => None,
+        };
+        let background_image = match &config.desktop.image {
+            Some(path) => Some(image::open(dir.join(path))
+                .map_err(|e|format!("could not load background image {:?}: {}", path, e))?
+                .into_rgba8()),
+            None => None,
+        };
+        Ok(Theme {
+            border: Insets { left: b.left, right: b.right, top: b.top, bottom: b.bottom },
+            title_height: config.title.height,
+            resize: Dimensions { width: b.resize_handle, height: b.resize_handle },
+            active_border: theme_color(&b.active)?,
+            inactive_border: theme_color(&b.inactive)?,
+            active_title: theme_color(&config.title.active)?,
+            inactive_title: theme_color(&config.title.inactive)?,
+            title_text: theme_color(&config.title.text)?,
+            title_font,
+            close_glyph: if config.title.close_glyph == 0 { None } else { Some(config.title.close_glyph) },
+            background: theme_color(&config.desktop.background)?,
+            background_image,
+            wallpaper_mode: config.desktop.mode,
+            cursor_glyph: config.cursor.glyph,
+            cursor_color: theme_color(&config.cursor.color)?,
+            shadow_size: config.effects.shadow_size,
+            shadow_color: theme_color(&config.effects.shadow_color)?,
+            dim: theme_color(&config.effects.dim)?,
+        })
+    }
+    //everything drawn around a window's contents: the border plus the title bar.
+    //drawing and hit testing both go through this so they always agree.
+    pub fn frame(&self) -> Insets {
+        Insets {
+            left: self.border.left,
+            right: self.border.right,
+            top: self.border.top + self.title_height,
+            bottom: self.border.bottom,
+        }
+    }
+    //the window with its frame around it
+    pub fn outer(&self, win:&Window) -> Bounds {
+        let frame = self.frame();
+        let b = win.bounds();
+        Bounds {
+            x: b.x - frame.left,
+            y: b.y - frame.top,
+            width: frame.left + b.width + frame.right,
+            height: frame.top + b.height + frame.bottom,
+        }
+    }
+    pub fn title_bar(&self, win:&Window) -> Bounds {
+        Bounds {
+            x: win.x - self.border.left,
+            y: win.y - self.title_height,
+            width: self.border.left + win.bounds().width + self.border.right,
+            height: self.title_height,
+        }
+    }
+    //a square at the right end of the title bar, if the theme has a close button
+    pub fn close_button(&self, win:&Window) -> Option<Bounds> {
+        self.close_glyph?;
+        Some(Bounds {
+            x: win.x + win.bounds().width - self.title_height,
+            y: win.y - self.title_height,
+            width: self.title_height,
+            height: self.title_height,
+        })
+    }
+}
+
+fn theme_color(name:&str) -> Result<Color, String> {
+    lookup_color(name).map_err(|e|e.to_string())
+}
+
+//reloads the theme when its file changes, so a theme can be edited while the screen runs
+pub struct ThemeWatcher {
+    path:PathBuf,
+    //from the screen config, reapplied on every reload
+    decorations:DecorationConfig,
+    colors:ColorConfig,
+    modified:Option<SystemTime>,
+    last_check:Instant,
+}
+
+impl ThemeWatcher {
+    pub fn new(path:PathBuf) -> ThemeWatcher {
+        let modified = modified_time(&path);
+        ThemeWatcher { path, decorations: Default::default(), colors: Default::default(), modified, last_check: Instant::now() }
+    }
+    //keep laying the config's deprecated [decorations] and [colors] over the theme
+    pub fn with_deprecated(mut self, decorations:&DecorationConfig, colors:&ColorConfig) -> ThemeWatcher {
+        self.decorations = decorations.clone();
+        self.colors = colors.clone();
+        self
+    }
+    //None if nothing changed. a theme that fails to load is reported once, and the
+    //screen keeps the one it has until the file is fixed
+    pub fn poll(&mut self) -> Option<Result<Theme, String>> {
+        if self.last_check.elapsed() < RELOAD_INTERVAL { return None; }
+        self.last_check = Instant::now();
+        let modified = modified_time(&self.path);
+        if modified == self.modified { return None; }
+        self.modified = modified;
+        Some(Theme::load_with(Some(&self.path), &self.decorations, &self.colors))
+    }
+}
+
+fn modified_time(path:&Path) -> Option<SystemTime> {
+    metadata(path).and_then(|m| m.modified()).ok()
+}
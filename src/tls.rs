@@ -0,0 +1,118 @@
+use std::fs::read;
+use std::path::PathBuf;
+use native_tls::{TlsConnector, Certificate};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{client_async_with_config, MaybeTlsStream};
+use tokio_tungstenite::tungstenite::Error as WsError;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+use crate::connection::WsStream;
+
+//how to reach the server. the token is sent both as a bearer Authorization header
+//and in ScreenStart, for servers which can't see the handshake headers.
+pub struct ConnectOptions {
+    //extra CA certificates to trust, in PEM. may hold several certs
+    pub ca_cert:Option<PathBuf>,
+    //the server's own certificate, in PEM. the server has to present exactly this one (or one
+    //of them, if the file holds several), and the CA and hostname checks are skipped. use with
+    //self signed servers
+    pub pin_cert:Option<PathBuf>,
+    pub token:Option<String>,
+}
+
+//the handshake request, with the auth header if there is a token
+pub fn make_request(server:&str, opts:&ConnectOptions) -> Result<Request, String> {
+    let mut request = server.into_client_request()
+        .map_err(|e|format!("bad server url {}: {}", server, e))?;
+    if let Some(token) = &opts.token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_|"the auth token can't be sent in a header".to_string())?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    Ok(request)
+}
+
+pub fn make_tls_connector(opts:&ConnectOptions) -> Result<TlsConnector, String> {
+    let mut builder = TlsConnector::builder();
+    if let Some(path) = &opts.ca_cert {
+        for cert in load_certs(path)? {
+            builder.add_root_certificate(cert);
+        }
+    }
+    //a pinned connection is checked against the pin instead, see connect_pinned
+    if opts.pin_cert.is_some() {
+        builder.danger_accept_invalid_certs(true);
+        builder.danger_accept_invalid_hostnames(true);
+    }
+    builder.build().map_err(|e|format!("could not set up TLS: {}", e))
+}
+
+//Certificate::from_pem only reads the first cert, so split bundles up ourselves
+fn load_certs(path:&PathBuf) -> Result<Vec<Certificate>, String> {
+    let bytes = read(path).map_err(|e|format!("could not read certificate {:?}: {}", path, e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let end = "-----END CERTIFICATE-----";
+    let mut certs = vec![];
+    for chunk in text.split_inclusive(end) {
+        if !chunk.contains(end) { continue; }
+        let cert = Certificate::from_pem(chunk.trim().as_bytes())
+            .map_err(|e|format!("bad certificate in {:?}: {}", path, e))?;
+        certs.push(cert);
+    }
+    if certs.is_empty() {
+        return Err(format!("no PEM certificates found in {:?}", path));
+    }
+    Ok(certs)
+}
+
+//the pinned certificates, as DER to compare with what the server presents
+pub fn load_pins(path:&PathBuf) -> Result<Vec<Vec<u8>>, String> {
+    load_certs(path)?.iter()
+        .map(|cert| cert.to_der().map_err(|e|format!("bad certificate in {:?}: {}", path, e)))
+        .collect()
+}
+
+pub fn check_pin(server:&str, pins:&[Vec<u8>], presented:Option<&Certificate>) -> Result<(), String> {
+    let der = match presented {
+        Some(cert) => cert.to_der().map_err(|e|format!("bad certificate from {}: {}", server, e))?,
+        None => return Err(format!("{} sent no certificate to check against --pin-cert", server)),
+    };
+    if pins.iter().any(|pin| *pin == der) {
+        Ok(())
+    } else {
+        Err(format!("{} presented a certificate that doesn't match --pin-cert", server))
+    }
+}
+
+//the TLS handshake is done here rather than by the websocket crate, so the server's certificate
+//can be checked before the handshake request, and the token in it, goes out
+pub async fn connect_pinned(server:&str, request:Request, connector:TlsConnector, pins:&[Vec<u8>]) -> Result<WsStream, String> {
+    let host = request.uri().host()
+        .ok_or_else(|| format!("bad server url {}: no host", server))?
+        .trim_start_matches('[').trim_end_matches(']')
+        .to_string();
+    let port = request.uri().port_u16().unwrap_or(443);
+    let tcp = TcpStream::connect((host.as_str(), port)).await
+        .map_err(|e|format!("could not reach {}: {}", server, e))?;
+    let tls = tokio_native_tls::TlsConnector::from(connector).connect(&host, tcp).await
+        .map_err(|e|format!("TLS error talking to {}: {}", server, e))?;
+    let presented = tls.get_ref().peer_certificate()
+        .map_err(|e|format!("TLS error talking to {}: {}", server, e))?;
+    check_pin(server, pins, presented.as_ref())?;
+    let (ws, _) = client_async_with_config(request, MaybeTlsStream::NativeTls(tls), None).await
+        .map_err(|e|describe_connect_error(server, e))?;
+    Ok(ws)
+}
+
+//turn the websocket crate's errors into something a person setting up a screen can act on
+pub fn describe_connect_error(server:&str, err:WsError) -> String {
+    match err {
+        WsError::Tls(e) => format!("TLS error talking to {}: {}. check --ca-cert or --pin-cert", server, e),
+        WsError::Http(response) => format!("{} refused the connection ({}). check the auth token", server, response.status()),
+        WsError::Io(e) => format!("could not reach {}: {}", server, e),
+        WsError::Url(e) => format!("bad server url {}: {}", server, e),
+        e => format!("could not connect to {}: {}", server, e),
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use crate::error::{ClientError, ClientResult};
+use crate::messages::{RenderMessage, any_graphics_message};
+use crate::window::Window;
+use crate::scaling::MAX_WINDOW_SCALE;
+
+//biggest window texture we will try to make
+const MAX_WINDOW_SIZE:i64 = 8192;
+//each window costs a texture, so a misbehaving app can't open them forever
+pub const MAX_WINDOWS:usize = 64;
+//coordinates and sizes past this are certainly bogus, and could overflow once offsets are added
+const MAX_COORD:i64 = 1 << 16;
+
+//the window a render message is about, used to find the app to report errors to
+pub fn message_window(msg:&RenderMessage) -> Option<String> {
+    match msg {
+        RenderMessage::OpenWindow(m) => Some(m.window.id.clone()),
+        RenderMessage::WindowSetSize(m) => Some(m.window.clone()),
+        RenderMessage::WindowSetPosition(m) => Some(m.window.clone()),
+        RenderMessage::CloseWindow(m) => Some(m.window.id.clone()),
+        RenderMessage::CreateChildWindow(m) => Some(m.parent.clone()),
+        RenderMessage::CloseChildWindow(m) => Some(m.window.clone()),
+        RenderMessage::DrawPixel(m) => Some(m.window.clone()),
+        RenderMessage::DrawImage(m) => Some(m.window.clone()),
+        RenderMessage::FillRect(m) => Some(m.window.clone()),
+        RenderMessage::CopyRect(m) => Some(m.window.clone()),
+        RenderMessage::SetDoubleBuffered(m) => Some(m.window.clone()),
+        RenderMessage::Commit(m) => Some(m.window.clone()),
+        RenderMessage::SetOpacity(m) => Some(m.window.clone()),
+        RenderMessage::SetMinimized(m) => Some(m.window.clone()),
+        RenderMessage::SetScale(m) => Some(m.window.clone()),
+        _ => None,
+    }
+}
+
+//check a message before the backend touches it. Err rejects the whole message.
+//for groups and window lists the bad entries are removed instead, and returned
+//so they can be reported while the rest is applied.
+pub fn validate_render_message(msg:&mut RenderMessage, windows:&HashMap<String, Window>) -> ClientResult<Vec<ClientError>> {
+    let mut skipped:Vec<ClientError> = vec![];
+    match msg {
+        RenderMessage::OpenWindow(m) => {
+            check_window_size(&m.window.id, m.window.width, m.window.height)?;
+            check_window_count(windows, &m.window.id, 0)?;
+        }
+        RenderMessage::CreateChildWindow(m) => {
+            check_known(windows, &m.parent)?;
+            check_window_size(&m.window.id, m.window.width, m.window.height)?;
+            check_window_count(windows, &m.window.id, 0)?;
+        }
+        RenderMessage::WindowSetSize(m) => {
+            check_known(windows, &m.window)?;
+            check_window_size(&m.window, m.width, m.height)?;
+        }
+        RenderMessage::WindowSetPosition(m) => {
+            check_known(windows, &m.window)?;
+            check_coords(&m.window, &[m.x, m.y])?;
+        }
+        RenderMessage::WindowList(m) => {
+            let mut added = 0;
+            m.windows.retain(|_, info| {
+                let res = check_window_size(&info.id, info.width, info.height)
+                    .and_then(|_| check_window_count(windows, &info.id, added));
+                match res {
+                    Ok(()) => {
+                        if !windows.contains_key(&info.id) { added += 1; }
+                        true
+                    }
+                    Err(e) => {
+                        skipped.push(e);
+                        false
+                    }
+                }
+            });
+        }
+        RenderMessage::DrawPixel(m) => {
+            check_known(windows, &m.window)?;
+            check_coords(&m.window, &[m.x, m.y])?;
+        }
+        RenderMessage::FillRect(m) => {
+            check_known(windows, &m.window)?;
+            check_coords(&m.window, &[m.x, m.y])?;
+            check_draw_size(&m.window, m.width, m.height)?;
+        }
+        RenderMessage::DrawImage(m) => {
+            check_known(windows, &m.window)?;
+            check_coords(&m.window, &[m.x, m.y])?;
+            check_image(&m.window, m.width, m.height, m.pixels.len())?;
+        }
+        RenderMessage::CopyRect(m) => {
+            check_known(windows, &m.window)?;
+            check_coords(&m.window, &[m.x, m.y, m.dx, m.dy])?;
+            check_draw_size(&m.window, m.width, m.height)?;
+        }
+        RenderMessage::SetDoubleBuffered(m) => check_known(windows, &m.window)?,
+        RenderMessage::Commit(m) => check_known(windows, &m.window)?,
+        RenderMessage::SetMinimized(m) => check_known(windows, &m.window)?,
+        RenderMessage::SetScale(m) => {
+            check_known(windows, &m.window)?;
+            if m.scale < 1 || m.scale > MAX_WINDOW_SCALE {
+                return Err(ClientError::invalid(&m.window, format!("window scale {} must be from 1 to {}", m.scale, MAX_WINDOW_SCALE)));
+            }
+        }
+        RenderMessage::SetOpacity(m) => {
+            check_known(windows, &m.window)?;
+            if !(0.0..=1.0).contains(&m.opacity) {
+                return Err(ClientError::invalid(&m.window, format!("opacity {} must be between 0 and 1", m.opacity)));
+            }
+        }
+        RenderMessage::Group(m) => {
+            m.messages.retain(|member| {
+                match validate_member(member, windows) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        skipped.push(e);
+                        false
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+    Ok(skipped)
+}
+
+fn validate_member(m:&any_graphics_message, windows:&HashMap<String, Window>) -> ClientResult<()> {
+    match m {
+        any_graphics_message::MAKE_DrawPixel_name { window, x, y, .. } => {
+            check_known(windows, window)?;
+            check_coords(window, &[*x, *y])
+        }
+        any_graphics_message::MAKE_DrawRect_name { window, x, y, width, height, .. } => {
+            check_known(windows, window)?;
+            check_coords(window, &[*x, *y])?;
+            check_draw_size(window, *width, *height)
+        }
+        any_graphics_message::MAKE_DrawImage_name { window, x, y, width, height, pixels, .. } => {
+            check_known(windows, window)?;
+            check_coords(window, &[*x, *y])?;
+            check_image(window, *width, *height, pixels.len())
+        }
+        any_graphics_message::CopyRect { window, x, y, width, height, dx, dy } => {
+            check_known(windows, window)?;
+            check_coords(window, &[*x, *y, *dx, *dy])?;
+            check_draw_size(window, *width, *height)
+        }
+        any_graphics_message::Commit { window } => check_known(windows, window),
+        any_graphics_message::PushTranslate { x, y } => check_coords("", &[*x, *y]),
+        any_graphics_message::PushClip { x, y, width, height } => {
+            check_coords("", &[*x, *y])?;
+            check_draw_size("", *width, *height)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_known(windows:&HashMap<String, Window>, window:&str) -> ClientResult<()> {
+    if windows.contains_key(window) {
+        Ok(())
+    } else {
+        Err(ClientError::UnknownWindow(window.to_string()))
+    }
+}
+
+//pending is how many new windows earlier entries of the same message will add
+fn check_window_count(windows:&HashMap<String, Window>, window:&str, pending:usize) -> ClientResult<()> {
+    if !windows.contains_key(window) && windows.len() + pending >= MAX_WINDOWS {
+        return Err(ClientError::invalid(window, format!("too many windows, the limit is {}", MAX_WINDOWS)));
+    }
+    Ok(())
+}
+
+fn check_window_size(window:&str, width:i64, height:i64) -> ClientResult<()> {
+    if width < 1 || height < 1 || width > MAX_WINDOW_SIZE || height > MAX_WINDOW_SIZE {
+        return Err(ClientError::invalid(window, format!("window size {}x{} must be between 1 and {}", width, height, MAX_WINDOW_SIZE)));
+    }
+    Ok(())
+}
+
+fn check_coords(window:&str, coords:&[i64]) -> ClientResult<()> {
+    if let Some(c) = coords.iter().find(|c| **c < -MAX_COORD || **c > MAX_COORD) {
+        return Err(ClientError::invalid(window, format!("coordinate {} is out of range", c)));
+    }
+    Ok(())
+}
+
+fn check_draw_size(window:&str, width:i64, height:i64) -> ClientResult<()> {
+    if width < 0 || height < 0 || width > MAX_COORD || height > MAX_COORD {
+        return Err(ClientError::invalid(window, format!("size {}x{} must be between 0 and {}", width, height, MAX_COORD)));
+    }
+    Ok(())
+}
+
+//images are always read as 4 bytes per pixel
+pub fn check_image(window:&str, width:i64, height:i64, len:usize) -> ClientResult<()> {
+    check_draw_size(window, width, height)?;
+    let needed = (width as u64).saturating_mul(height as u64).saturating_mul(4);
+    if (len as u64) < needed {
+        return Err(ClientError::invalid(window, format!("pixel buffer has {} bytes, {}x{} needs {}", len, width, height, needed)));
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::messages::{WindowInfo, window_info};
+use crate::namespace::remote_id;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rect {
@@ -11,11 +12,13 @@ pub struct Rect {
     pub color:String,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Dimensions {
     pub width:i32,
     pub height:i32,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Bounds {
     pub x:i32,
     pub y:i32,
@@ -23,6 +26,19 @@ pub struct Bounds {
     pub height:i32,
 }
 
+impl Bounds {
+    pub fn contains(&self, pt:&Point) -> bool {
+        pt.x >= self.x && pt.x < self.x + self.width && pt.y >= self.y && pt.y < self.y + self.height
+    }
+    //moved as little as possible to be inside the area. if it is too big to fit, its top left
+    //corner is kept inside instead, so a title bar can still be grabbed
+    pub fn keep_inside(&self, area:&Bounds) -> Bounds {
+        let x = self.x.min(area.x + area.width - self.width).max(area.x);
+        let y = self.y.min(area.y + area.height - self.height).max(area.y);
+        Bounds { x, y, width: self.width, height: self.height }
+    }
+}
+
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Point {
@@ -30,8 +46,12 @@ pub struct Point {
     pub y:i32,
 }
 
+#[derive(Clone)]
 pub struct Window {
+    //the window key, which includes the connection. see namespace.rs
     pub id:String,
+    //which server the window belongs to
+    pub connection:usize,
     pub x:i32,
     pub y:i32,
     pub width:i32,
@@ -39,54 +59,102 @@ pub struct Window {
     pub owner:String,
     pub window_type:String,
     pub title:String,
+    //0 is invisible, 255 fully opaque. set by the app or the user
+    pub opacity:u8,
+    //not drawn or clickable until the app or a dock restores it
+    pub minimized:bool,
+    //each of the app's pixels is drawn this many virtual pixels wide, on top of the screen scale
+    pub scale:u32,
 }
 
 pub const WINDOW_TYPE_PLAIN:&str = "PLAIN";
 
+//an empty plain window at the origin, fully opaque and unscaled
+impl Default for Window {
+    fn default() -> Self {
+        Window {
+            id: String::new(),
+            connection: 0,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            owner: String::new(),
+            window_type: WINDOW_TYPE_PLAIN.to_string(),
+            title: String::new(),
+            opacity: 255,
+            minimized: false,
+            scale: 1,
+        }
+    }
+}
+
 
 impl Window {
-    pub fn from_info(info:&WindowInfo) -> Window {
+    pub fn from_info(info:&WindowInfo, connection:usize) -> Window {
         Window {
             id:info.id.clone(),
+            connection,
             x:info.x,
             y:info.y,
             width: info.width,
             height: info.height,
             owner: info.owner.clone(),
             window_type: info.window_type.clone(),
-            title: "title".to_string()
+            title: "title".to_string(),
+            ..Default::default()
         }
     }
-    pub fn from_info2(info:&window_info) -> Window {
+    pub fn from_info2(info:&window_info, connection:usize) -> Window {
         Window {
             id: info.id.clone(),
+            connection,
             x: info.x as i32,
             y: info.y as i32,
             width: info.width as i32,
             height: info.height as i32,
             owner: info.owner.clone(),
             window_type: info.window_type.clone(),
-            title: "title".to_string()
+            title: "title".to_string(),
+            ..Default::default()
         }
     }
+    //the id to use in messages to the server
+    pub fn remote_id(&self) -> &str {
+        remote_id(&self.id)
+    }
+
+    //where the window is on the screen, in virtual pixels. width and height are in the app's pixels
+    pub fn bounds(&self) -> Bounds {
+        let scale = self.scale.max(1) as i32;
+        Bounds { x: self.x, y: self.y, width: self.width * scale, height: self.height * scale }
+    }
+    //a screen point in the app's pixels
+    pub fn to_local(&self, pt:&Point) -> Point {
+        let scale = self.scale.max(1) as i32;
+        Point { x: (pt.x - self.x).div_euclid(scale), y: (pt.y - self.y).div_euclid(scale) }
+    }
 
     pub fn contains(&self, pt:&Point) -> bool {
-        if pt.x < self.x { return false; }
-        if pt.x > (self.x + self.width) { return false; }
-        if pt.y < self.y { return false; }
-        if pt.y > (self.y + self.height) { return false; }
+        let b = self.bounds();
+        if pt.x < b.x { return false; }
+        if pt.x > (b.x + b.width) { return false; }
+        if pt.y < b.y { return false; }
+        if pt.y > (b.y + b.height) { return false; }
         return true
     }
     pub fn border_contains(&self, pt:&Point, border:&Insets) -> bool {
-        if pt.x < self.x-border.left { return false; }
-        if pt.x > (self.x + self.width+border.right) { return false; }
-        if pt.y < self.y-border.top { return false; }
-        if pt.y > (self.y + self.height+border.bottom) { return false; }
+        let b = self.bounds();
+        if pt.x < b.x-border.left { return false; }
+        if pt.x > (b.x + b.width+border.right) { return false; }
+        if pt.y < b.y-border.top { return false; }
+        if pt.y > (b.y + b.height+border.bottom) { return false; }
         return true
     }
     pub fn resize_contains(&self, pt:&Point, size:&Dimensions) -> bool {
-        let corner_x = self.x + self.width - size.width;
-        let corner_y = self.y + self.height - size.height;
+        let b = self.bounds();
+        let corner_x = b.x + b.width - size.width;
+        let corner_y = b.y + b.height - size.height;
         if pt.x < corner_x { return false; }
         if pt.y < corner_y { return false; }
         if pt.x > corner_x + size.width { return false; }
@@ -95,6 +163,7 @@ impl Window {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Insets {
     pub left:i32,
     pub right:i32,
@@ -0,0 +1,157 @@
+//decoding binary drawing frames, and drawing them on the headless screen
+mod common;
+
+use rust_screen::binary::parse_binary;
+use rust_screen::messages::{RenderMessage, any_graphics_message};
+use rust_screen::namespace::window_key;
+use common::{on_screen, rgba};
+
+fn string(frame:&mut Vec<u8>, s:&str) {
+    frame.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    frame.extend_from_slice(s.as_bytes());
+}
+
+fn ints(frame:&mut Vec<u8>, values:&[i32]) {
+    for v in values {
+        frame.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn pixel_frame(window:&str, x:i32, y:i32, color:[u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0x01];
+    string(&mut frame, window);
+    ints(&mut frame, &[x, y]);
+    frame.extend_from_slice(&color);
+    frame
+}
+
+fn rect_frame(window:&str, x:i32, y:i32, width:i32, height:i32, color:[u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0x02];
+    string(&mut frame, window);
+    ints(&mut frame, &[x, y, width, height]);
+    frame.extend_from_slice(&color);
+    frame
+}
+
+fn image_frame(window:&str, x:i32, y:i32, width:i32, height:i32, pixels:&[u8]) -> Vec<u8> {
+    let mut frame = vec![0x03];
+    string(&mut frame, window);
+    ints(&mut frame, &[x, y, width, height]);
+    frame.extend_from_slice(pixels);
+    frame
+}
+
+fn group_frame(members:&[Vec<u8>]) -> Vec<u8> {
+    let mut frame = vec![0x04];
+    frame.extend_from_slice(&(members.len() as u16).to_le_bytes());
+    for member in members {
+        frame.extend_from_slice(member);
+    }
+    frame
+}
+
+#[test]
+fn pixel() {
+    match parse_binary(&pixel_frame("w1", -3, 7, [255, 0, 128, 64])) {
+        Ok(RenderMessage::DrawPixel(m)) => {
+            assert_eq!((m.window.as_str(), m.x, m.y, m.color.as_str()), ("w1", -3, 7, "#ff008040"));
+        }
+        other => panic!("expected a pixel, got {:?}", other),
+    }
+}
+
+#[test]
+fn rect() {
+    match parse_binary(&rect_frame("w1", 1, 2, 30, 40, [0, 0, 0, 255])) {
+        Ok(RenderMessage::FillRect(m)) => {
+            assert_eq!((m.window.as_str(), m.x, m.y, m.width, m.height, m.color.as_str()), ("w1", 1, 2, 30, 40, "#000000ff"));
+        }
+        other => panic!("expected a rect, got {:?}", other),
+    }
+}
+
+#[test]
+fn image() {
+    let pixels:Vec<u8> = (0..24).collect();
+    match parse_binary(&image_frame("w1", 5, 6, 3, 2, &pixels)) {
+        Ok(RenderMessage::DrawImage(m)) => {
+            assert_eq!((m.window.as_str(), m.x, m.y, m.width, m.height, m.depth, m.channels), ("w1", 5, 6, 3, 2, 8, 4));
+            assert_eq!(m.pixels, pixels);
+        }
+        other => panic!("expected an image, got {:?}", other),
+    }
+}
+
+#[test]
+fn group() {
+    let frame = group_frame(&[
+        pixel_frame("w1", 0, 0, [1, 2, 3, 4]),
+        rect_frame("w1", 0, 0, 2, 2, [1, 2, 3, 4]),
+        image_frame("w1", 0, 0, 1, 1, &[1, 2, 3, 4]),
+    ]);
+    match parse_binary(&frame) {
+        Ok(RenderMessage::Group(m)) => {
+            assert_eq!(m.messages.len(), 3);
+            assert!(matches!(m.messages[0], any_graphics_message::MAKE_DrawPixel_name { .. }));
+            assert!(matches!(m.messages[1], any_graphics_message::MAKE_DrawRect_name { .. }));
+            assert!(matches!(m.messages[2], any_graphics_message::MAKE_DrawImage_name { .. }));
+        }
+        other => panic!("expected a group, got {:?}", other),
+    }
+    assert!(matches!(parse_binary(&group_frame(&[])), Ok(RenderMessage::Group(m)) if m.messages.is_empty()));
+}
+
+#[test]
+fn truncated_frames_are_errors() {
+    let frames = vec![
+        pixel_frame("w1", 0, 0, [0, 0, 0, 255]),
+        rect_frame("w1", 0, 0, 2, 2, [0, 0, 0, 255]),
+        image_frame("w1", 0, 0, 2, 2, &[0; 16]),
+        group_frame(&[pixel_frame("w1", 0, 0, [0, 0, 0, 255]), pixel_frame("w1", 1, 0, [0, 0, 0, 255])]),
+    ];
+    for frame in frames {
+        for cut in 0..frame.len() {
+            assert!(parse_binary(&frame[..cut]).is_err(), "frame {:?} cut to {} bytes should not parse", frame, cut);
+        }
+        assert!(parse_binary(&frame).is_ok());
+    }
+}
+
+#[test]
+fn bad_frames_are_errors() {
+    assert!(parse_binary(&[0x7f, 0, 0]).is_err());
+    assert!(parse_binary(&[]).is_err());
+    //groups don't nest
+    assert!(parse_binary(&group_frame(&[group_frame(&[])])).is_err());
+    assert!(parse_binary(&image_frame("w1", 0, 0, -1, 4, &[])).is_err());
+    //the size would overflow, or is far more than the frame holds
+    assert!(parse_binary(&image_frame("w1", 0, 0, i32::MAX, i32::MAX, &[0; 16])).is_err());
+    let mut not_utf8 = vec![0x01];
+    not_utf8.extend_from_slice(&2u16.to_le_bytes());
+    not_utf8.extend_from_slice(&[0xff, 0xfe]);
+    ints(&mut not_utf8, &[0, 0]);
+    not_utf8.extend_from_slice(&[0, 0, 0, 255]);
+    assert!(parse_binary(&not_utf8).is_err());
+}
+
+#[test]
+fn frames_draw_like_json() {
+    on_screen(|screen| {
+        screen.handle_text(r#"{"type":"MAKE_window_list_name","windows":{"w1":{"id":"w1","x":0,"y":0,"width":8,"height":8,"owner":"app","window_type":"PLAIN"}}}"#);
+        let key = window_key(0, "w1");
+        let frames = vec![
+            rect_frame("w1", 0, 0, 2, 2, [255, 0, 0, 255]),
+            group_frame(&[
+                pixel_frame("w1", 3, 0, [0, 255, 0, 255]),
+                image_frame("w1", 4, 0, 1, 1, &[0, 0, 255, 255]),
+            ]),
+        ];
+        let messages = frames.iter().map(|f| parse_binary(f).unwrap()).collect();
+        screen.apply(messages);
+        let pixels = screen.window_pixels(&key).unwrap();
+        assert_eq!(rgba(&pixels, 8, 1, 1), (255, 0, 0, 255));
+        assert_eq!(rgba(&pixels, 8, 2, 0), (0, 0, 0, 255));
+        assert_eq!(rgba(&pixels, 8, 3, 0), (0, 255, 0, 255));
+        assert_eq!(rgba(&pixels, 8, 4, 0), (0, 0, 255, 255));
+    });
+}
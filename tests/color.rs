@@ -0,0 +1,71 @@
+//the CSS color syntax accepted in messages, themes and the config
+use sdl2::pixels::Color;
+use rust_screen::color::{parse_color, lookup_color};
+
+fn rgba(text:&str) -> (u8, u8, u8, u8) {
+    let c = parse_color(text).unwrap_or_else(|| panic!("{} didn't parse", text));
+    (c.r, c.g, c.b, c.a)
+}
+
+#[test]
+fn names() {
+    assert_eq!(rgba("red"), (255, 0, 0, 255));
+    assert_eq!(rgba("rebeccapurple"), (0x66, 0x33, 0x99, 255));
+    assert_eq!(rgba("LightGoldenRodYellow"), (0xfa, 0xfa, 0xd2, 255));
+    assert_eq!(rgba(" teal "), (0, 128, 128, 255));
+    assert_eq!(rgba("transparent"), (0, 0, 0, 0));
+    assert!(parse_color("reddish").is_none());
+    assert!(parse_color("").is_none());
+}
+
+//the names the screen knew before CSS colors. only green moved, to the CSS value
+#[test]
+fn names_from_before_css_colors() {
+    assert_eq!(rgba("red"), (255, 0, 0, 255));
+    assert_eq!(rgba("black"), (0, 0, 0, 255));
+    assert_eq!(rgba("blue"), (0, 0, 255, 255));
+    assert_eq!(rgba("white"), (255, 255, 255, 255));
+    assert_eq!(rgba("green"), (0, 128, 0, 255));
+    assert_eq!(rgba("lime"), (0, 255, 0, 255));
+    assert_eq!(rgba("yellow"), (255, 255, 0, 255));
+    assert_eq!(rgba("grey"), (128, 128, 128, 255));
+    assert_eq!(rgba("gray"), (128, 128, 128, 255));
+    assert_eq!(rgba("magenta"), (255, 0, 255, 255));
+    assert_eq!(rgba("teal"), (0, 128, 128, 255));
+    assert_eq!(rgba("aqua"), (0, 255, 255, 255));
+    assert_eq!(rgba("cyan"), (0, 255, 255, 255));
+}
+
+#[test]
+fn hex() {
+    assert_eq!(rgba("#f0a"), (255, 0, 170, 255));
+    assert_eq!(rgba("#f0a8"), (255, 0, 170, 136));
+    assert_eq!(rgba("#336699"), (0x33, 0x66, 0x99, 255));
+    assert_eq!(rgba("#33669980"), (0x33, 0x66, 0x99, 0x80));
+    for bad in &["#", "#12", "#12345", "#1234567", "#zzzzzz", "#ééé"] {
+        assert!(parse_color(bad).is_none(), "{} should not parse", bad);
+    }
+}
+
+#[test]
+fn functions() {
+    assert_eq!(rgba("rgb(255, 128, 0)"), (255, 128, 0, 255));
+    assert_eq!(rgba("rgba(255, 128, 0, 0.5)"), (255, 128, 0, 128));
+    assert_eq!(rgba("rgb(100%, 50%, 0%)"), (255, 128, 0, 255));
+    assert_eq!(rgba("rgb(255 128 0 / 25%)"), (255, 128, 0, 64));
+    assert_eq!(rgba("rgb(300, -5, 0)"), (255, 0, 0, 255));
+    assert_eq!(rgba("hsl(0, 100%, 50%)"), (255, 0, 0, 255));
+    assert_eq!(rgba("hsl(120deg 100% 25%)"), (0, 128, 0, 255));
+    assert_eq!(rgba("hsla(240, 100%, 50%, 0.5)"), (0, 0, 255, 128));
+    assert_eq!(rgba("hsl(-120, 100%, 50%)"), (0, 0, 255, 255));
+    assert_eq!(rgba("hsl(0, 0%, 100%)"), (255, 255, 255, 255));
+    for bad in &["rgb(1, 2)", "rgb(1, 2, 3, 4, 5)", "rgb(a, b, c)", "rgb(1, 2, 3", "hsl(0, 100, 50)", "cmyk(0, 0, 0, 0)", "rgb(nan, 0, 0)"] {
+        assert!(parse_color(bad).is_none(), "{} should not parse", bad);
+    }
+}
+
+#[test]
+fn bad_colors_are_errors() {
+    assert!(lookup_color("#nothex").is_err());
+    assert_eq!(lookup_color("black").unwrap(), Color::RGBA(0, 0, 0, 255));
+}
@@ -0,0 +1,70 @@
+//helpers shared by the integration tests. each test binary uses some of them
+#![allow(dead_code)]
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use rust_screen::headless::Headless;
+use rust_screen::window::{Window, Bounds, Dimensions};
+
+type Job = Box<dyn FnOnce(&mut Headless) + Send>;
+
+//SDL can only be started once per process, and its objects have to stay on the thread that
+//made them. so one thread owns the headless screen, and the tests take turns on it
+static SCREEN:Mutex<Option<Sender<Job>>> = Mutex::new(None);
+
+//run a test against the shared headless screen, starting from a blank one.
+//a failure in the test fails the calling test, not the screen thread
+pub fn on_screen<F>(test:F) where F: FnOnce(&mut Headless) + Send + 'static {
+    let jobs = SCREEN.lock().unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(start_screen)
+        .clone();
+    let (done_send, done) = channel();
+    jobs.send(Box::new(move |screen:&mut Headless| {
+        screen.reset();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| test(screen)));
+        let _ = done_send.send(result);
+    })).expect("the screen thread has stopped");
+    if let Err(failure) = done.recv().expect("the screen thread has stopped") {
+        panic::resume_unwind(failure);
+    }
+}
+
+fn start_screen() -> Sender<Job> {
+    let (jobs, queue) = channel::<Job>();
+    thread::spawn(move || {
+        let mut screen = Headless::new().expect("could not start the headless renderer");
+        for job in queue {
+            job(&mut screen);
+        }
+    });
+    jobs
+}
+
+//the RGBA pixel at x,y of an image width pixels across
+pub fn rgba(pixels:&[u8], width:usize, x:usize, y:usize) -> (u8, u8, u8, u8) {
+    let n = (y * width + x) * 4;
+    (pixels[n], pixels[n + 1], pixels[n + 2], pixels[n + 3])
+}
+
+//a screen and a window for the geometry tests
+pub const SCREEN_BOUNDS:Bounds = Bounds { x: 0, y: 0, width: 640, height: 480 };
+pub const SCREEN_SIZE:Dimensions = Dimensions { width: 640, height: 480 };
+
+pub fn window() -> Window {
+    Window {
+        id: "0/win".to_string(),
+        x: 50, y: 60, width: 100, height: 80,
+        owner: "app".to_string(),
+        title: "title".to_string(),
+        ..Default::default()
+    }
+}
+
+pub fn xywh(b:Bounds) -> (i32, i32, i32, i32) {
+    (b.x, b.y, b.width, b.height)
+}
+
+pub fn xy(b:Bounds) -> (i32, i32) {
+    (b.x, b.y)
+}
@@ -0,0 +1,107 @@
+//the config file: defaults, partial files, and the errors a typo should give
+use std::time::Duration;
+use rust_screen::config::{Config, LogLevel, EffectsConfig};
+use rust_screen::theme::Theme;
+
+#[test]
+fn empty_file_is_the_defaults() {
+    let config = Config::parse("").unwrap();
+    assert!(config.servers.is_empty());
+    assert_eq!((config.screen.width, config.screen.height), (512, 320));
+    //picked from the display
+    assert!(config.screen.scale.is_none());
+    assert!(config.theme.is_none());
+    //the old decoration settings now come from the theme unless they are set
+    assert_eq!(config.decorations.border_top, None);
+    assert_eq!(config.load_theme().unwrap().title_height, 10);
+    assert_eq!(config.logging.level, LogLevel::Info);
+}
+
+#[test]
+fn partial_sections_keep_the_other_defaults() {
+    let config = Config::parse(r#"
+servers = ["ws://localhost:8081", "ws://pi.local:8081"]
+theme = "themes/dark.toml"
+
+[screen]
+scale = 2
+
+[colors]
+background = "#336699"
+
+[keys]
+quit = "F10"
+
+[logging]
+level = "debug"
+"#).unwrap();
+    assert_eq!(config.servers.len(), 2);
+    assert_eq!(config.screen.scale, Some(2.0));
+    assert_eq!(config.screen.width, 512);
+    assert_eq!(config.colors.border, None);
+    assert_eq!(config.theme.as_ref().unwrap().to_str(), Some("themes/dark.toml"));
+    assert_eq!(config.logging.level, LogLevel::Debug);
+    let settings = config.settings().unwrap();
+    assert_eq!(settings.quit_key.name(), "F10");
+    assert_eq!(settings.opacity_up_key.name(), "F8");
+    let theme = Theme::load_with(None, &config.decorations, &config.colors).unwrap();
+    assert_eq!((theme.background.r, theme.background.g, theme.background.b), (0x33, 0x66, 0x99));
+}
+
+#[test]
+fn effects_can_be_turned_off() {
+    let settings = Config::default().settings().unwrap();
+    assert!(settings.effects.shadows && settings.effects.dim_inactive);
+    assert_eq!(settings.effects.open, Duration::from_millis(150));
+    let config = Config::parse("[effects]\nshadows = false\nopen_ms = 0").unwrap();
+    let settings = config.settings().unwrap();
+    assert!(!settings.effects.shadows);
+    assert!(settings.effects.dim_inactive);
+    assert_eq!(settings.effects.open, Duration::from_millis(0));
+    let off = EffectsConfig::none();
+    assert!(!off.shadows && !off.dim_inactive);
+    assert_eq!((off.open_ms, off.close_ms, off.minimize_ms), (0, 0, 0));
+}
+
+#[test]
+fn mistakes_are_reported() {
+    assert!(Config::parse("[screen]\nscael = 2").is_err());
+    assert!(Config::parse("[screen]\nscale = 0").is_err());
+    assert!(Config::parse("[screen]\nscale = 1.5").is_ok());
+    assert!(Config::parse("[keys]\nquit = \"NotAKey\"").is_err());
+    assert!(Config::parse("[colors]\nbackground = \"#zzzzzz\"").is_err());
+    assert!(Config::parse("[decorations]\nborder_top = -1").is_err());
+    assert!(Config::parse("[logging]\nlevel = \"loud\"").is_err());
+    assert!(Config::parse("[connection]\nping_interval = 0").is_err());
+    assert!(Config::parse("[connection]\nping_timeout = 0").is_err());
+    assert!(Config::parse("[connection]\nping_interval = 1\nping_timeout = 3").is_ok());
+}
+
+#[test]
+fn printed_config_reads_back_the_same() {
+    let mut config = Config::default();
+    config.servers = vec!["ws://localhost:8081".to_string()];
+    config.screen.fullscreen = true;
+    let text = config.to_toml().unwrap();
+    let again = Config::parse(&text).unwrap();
+    assert_eq!(again.servers, config.servers);
+    assert!(again.screen.fullscreen);
+    assert_eq!(again.to_toml().unwrap(), text);
+}
+
+#[test]
+fn deprecated_sections_are_laid_over_the_theme() {
+    let path = std::env::temp_dir().join(format!("rust-screen-config-theme-{}.toml", std::process::id()));
+    std::fs::write(&path, "[border]\nleft = 3\nright = 3\n\n[title]\nheight = 14\nactive = \"blue\"\n").unwrap();
+    let mut config = Config::parse("[decorations]\nborder_left = 5\n\n[colors]\nactive_border = \"lime\"").unwrap();
+    config.theme = Some(path.clone());
+    let theme = config.load_theme().unwrap();
+    assert_eq!((theme.border.left, theme.border.right, theme.title_height), (5, 3, 14));
+    assert_eq!((theme.active_title.r, theme.active_title.g, theme.active_title.b), (0, 255, 0));
+    assert_eq!((theme.active_border.r, theme.active_border.g, theme.active_border.b), (0, 255, 0));
+    let _ = std::fs::remove_file(&path);
+
+    //they only show up in a printed config when they are set
+    assert!(config.to_toml().unwrap().contains("border_left = 5"));
+    assert!(!Config::default().to_toml().unwrap().contains("decorations"));
+}
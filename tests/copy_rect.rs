@@ -0,0 +1,121 @@
+//window-copy-rect, read back from the window's texture on the headless screen
+mod common;
+
+use serde_json::json;
+use rust_screen::headless::Headless;
+use rust_screen::namespace::window_key;
+use common::{on_screen, rgba};
+
+const RED:(u8, u8, u8, u8) = (255, 0, 0, 255);
+const GREEN:(u8, u8, u8, u8) = (0, 255, 0, 255);
+const BLUE:(u8, u8, u8, u8) = (0, 0, 255, 255);
+const WHITE:(u8, u8, u8, u8) = (255, 255, 255, 255);
+const BLACK:(u8, u8, u8, u8) = (0, 0, 0, 255);
+
+//a 16x4 window with red, green, blue and white in the first four pixels of the top row
+fn striped_window(screen:&mut Headless) -> String {
+    screen.handle_text(&json!({
+        "type": "MAKE_window_list_name",
+        "windows": { "w1": { "id": "w1", "x": 0, "y": 0, "width": 16, "height": 4, "owner": "app", "window_type": "PLAIN" } },
+    }).to_string());
+    for (x, color) in ["red", "lime", "blue", "white"].iter().enumerate() {
+        screen.handle_text(&json!({ "type": "MAKE_DrawPixel_name", "window": "w1", "color": color, "x": x, "y": 0 }).to_string());
+    }
+    window_key(0, "w1")
+}
+
+fn copy(screen:&mut Headless, x:i64, y:i64, width:i64, height:i64, dx:i64, dy:i64) {
+    screen.handle_text(&json!({
+        "type": "window-copy-rect", "window": "w1",
+        "x": x, "y": y, "width": width, "height": height, "dx": dx, "dy": dy,
+    }).to_string());
+}
+
+fn top_row(screen:&mut Headless, key:&str) -> Vec<(u8, u8, u8, u8)> {
+    let pixels = screen.window_pixels(key).unwrap();
+    (0..16).map(|x| rgba(&pixels, 16, x, 0)).collect()
+}
+
+#[test]
+fn overlapping_copy_to_the_right_moves_every_pixel_once() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        copy(screen, 0, 0, 4, 1, 1, 0);
+        //copied left to right in place, red would have smeared across all four
+        assert_eq!(top_row(screen, &key)[0..6], [RED, RED, GREEN, BLUE, WHITE, BLACK]);
+    });
+}
+
+#[test]
+fn overlapping_copy_to_the_left() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        copy(screen, 1, 0, 3, 1, 0, 0);
+        assert_eq!(top_row(screen, &key)[0..5], [GREEN, BLUE, WHITE, WHITE, BLACK]);
+    });
+}
+
+#[test]
+fn scrolling_down() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        copy(screen, 0, 0, 16, 3, 0, 1);
+        let pixels = screen.window_pixels(&key).unwrap();
+        assert_eq!(rgba(&pixels, 16, 0, 0), RED);
+        assert_eq!(rgba(&pixels, 16, 0, 1), RED);
+        assert_eq!(rgba(&pixels, 16, 3, 1), WHITE);
+        assert_eq!(rgba(&pixels, 16, 0, 2), BLACK);
+    });
+}
+
+#[test]
+fn source_off_the_window_only_copies_the_part_on_it() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        //starting two pixels left of the window, so only red and green are there to copy
+        copy(screen, -2, 0, 4, 1, 8, 0);
+        assert_eq!(top_row(screen, &key)[8..12], [BLACK, BLACK, RED, GREEN]);
+    });
+}
+
+#[test]
+fn destination_off_the_window_is_cut_off() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        copy(screen, 0, 0, 4, 1, 14, 0);
+        assert_eq!(top_row(screen, &key)[12..16], [BLACK, BLACK, RED, GREEN]);
+        copy(screen, 0, 0, 4, 1, -3, 0);
+        assert_eq!(top_row(screen, &key)[0..4], [WHITE, GREEN, BLUE, WHITE]);
+        assert!(screen.check().is_ok());
+    });
+}
+
+#[test]
+fn nothing_to_copy_leaves_the_window_alone() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        let before = top_row(screen, &key);
+        copy(screen, 100, 100, 4, 4, 0, 0);
+        copy(screen, 0, 0, 0, 4, 8, 0);
+        //rejected by validation
+        copy(screen, 0, 0, -4, 1, 8, 0);
+        copy(screen, 0, 0, 4, 1, 1 << 20, 0);
+        assert_eq!(top_row(screen, &key), before);
+    });
+}
+
+#[test]
+fn copies_in_a_group_are_translated_and_clipped() {
+    on_screen(|screen| {
+        let key = striped_window(screen);
+        screen.handle_text(&json!({
+            "type": "group-message", "category": "test", "messages": [
+                { "type": "group-push-translate", "x": 8, "y": 0 },
+                { "type": "group-push-clip", "x": 0, "y": 0, "width": 2, "height": 1 },
+                { "type": "window-copy-rect", "window": "w1", "x": -8, "y": 0, "width": 4, "height": 1, "dx": 0, "dy": 0 },
+            ],
+        }).to_string());
+        //the copy lands at 8, but the clip only lets through its first two pixels
+        assert_eq!(top_row(screen, &key)[8..12], [RED, GREEN, BLACK, BLACK]);
+    });
+}
@@ -0,0 +1,68 @@
+//wallpaper layout and the desktop-set-wallpaper message
+use serde_json::json;
+use sdl2::rect::Rect;
+use rust_screen::desktop::{WallpaperMode, parse_wallpaper, wallpaper_rects};
+
+#[test]
+fn stretched_covers_the_screen() {
+    let rects = wallpaper_rects(WallpaperMode::Stretched, (10, 20), (640, 480), 2.0);
+    assert_eq!(rects, vec![Rect::new(0, 0, 640, 480)]);
+}
+
+#[test]
+fn scaled_keeps_the_aspect_ratio() {
+    //wide image on a 4:3 screen is letterboxed top and bottom
+    let rects = wallpaper_rects(WallpaperMode::Scaled, (200, 100), (640, 480), 1.0);
+    assert_eq!(rects, vec![Rect::new(0, 80, 640, 320)]);
+    //tall image is pillarboxed
+    let rects = wallpaper_rects(WallpaperMode::Scaled, (100, 200), (640, 480), 1.0);
+    assert_eq!(rects, vec![Rect::new(200, 0, 240, 480)]);
+}
+
+#[test]
+fn centered_follows_the_screen_scale() {
+    let rects = wallpaper_rects(WallpaperMode::Centered, (100, 50), (640, 480), 1.0);
+    assert_eq!(rects, vec![Rect::new(270, 215, 100, 50)]);
+    let rects = wallpaper_rects(WallpaperMode::Centered, (100, 50), (640, 480), 2.0);
+    assert_eq!(rects, vec![Rect::new(220, 190, 200, 100)]);
+    let rects = wallpaper_rects(WallpaperMode::Centered, (100, 50), (640, 480), 1.5);
+    assert_eq!(rects, vec![Rect::new(245, 202, 150, 75)]);
+    //bigger than the screen is cropped evenly
+    let rects = wallpaper_rects(WallpaperMode::Centered, (800, 600), (640, 480), 1.0);
+    assert_eq!(rects, vec![Rect::new(-80, -60, 800, 600)]);
+}
+
+#[test]
+fn tiles_cover_the_screen_and_stay_bounded() {
+    let rects = wallpaper_rects(WallpaperMode::Tiled, (100, 100), (250, 120), 1.0);
+    assert_eq!(rects.len(), 6);
+    assert_eq!(rects[5], Rect::new(200, 100, 100, 100));
+    let rects = wallpaper_rects(WallpaperMode::Tiled, (1, 1), (3840, 2160), 1.0);
+    assert!(rects.len() <= 4096);
+    let last = rects.last().unwrap();
+    assert!(last.right() >= 3840 && last.bottom() >= 2160);
+    assert!(wallpaper_rects(WallpaperMode::Tiled, (0, 10), (640, 480), 1.0).is_empty());
+}
+
+#[test]
+fn wallpaper_messages() {
+    let update = parse_wallpaper(json!({ "type": "desktop-set-wallpaper", "color": "teal", "mode": "tiled" })).unwrap();
+    assert_eq!(update.color.as_deref(), Some("teal"));
+    assert_eq!(update.mode, Some(WallpaperMode::Tiled));
+    assert!(update.image.is_none());
+
+    let update = parse_wallpaper(json!({
+        "type": "desktop-set-wallpaper",
+        "image": { "encoding": "raw", "width": 2, "height": 1, "data": base64::encode(&[255u8, 0, 0, 255, 0, 0, 255, 255]) },
+    })).unwrap();
+    let img = update.image.unwrap().unwrap();
+    assert_eq!((img.width(), img.height()), (2, 1));
+    assert_eq!(img.get_pixel(1, 0).0, [0, 0, 255, 255]);
+
+    let update = parse_wallpaper(json!({ "type": "desktop-set-wallpaper", "image": null })).unwrap();
+    assert!(matches!(update.image, Some(None)));
+
+    assert!(parse_wallpaper(json!({ "type": "desktop-set-wallpaper", "mode": "sideways" })).is_err());
+    assert!(parse_wallpaper(json!({ "type": "desktop-set-wallpaper", "color": 7 })).is_err());
+    assert!(parse_wallpaper(json!({ "type": "desktop-set-wallpaper", "image": { "width": 2, "height": 2, "pixels": [1, 2, 3] } })).is_err());
+}
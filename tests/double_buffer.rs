@@ -0,0 +1,122 @@
+//double buffered windows: drawing goes to the back buffer and only shows on commit
+mod common;
+
+use serde_json::json;
+use rust_screen::headless::Headless;
+use rust_screen::namespace::window_key;
+use common::{on_screen, rgba};
+
+const RED:(u8, u8, u8, u8) = (255, 0, 0, 255);
+const GREEN:(u8, u8, u8, u8) = (0, 255, 0, 255);
+const BLUE:(u8, u8, u8, u8) = (0, 0, 255, 255);
+const BLACK:(u8, u8, u8, u8) = (0, 0, 0, 255);
+
+fn double_buffered_window(screen:&mut Headless) -> String {
+    screen.handle_text(&json!({
+        "type": "MAKE_window_list_name",
+        "windows": { "w1": { "id": "w1", "x": 0, "y": 0, "width": 8, "height": 8, "owner": "app", "window_type": "PLAIN" } },
+    }).to_string());
+    set_double_buffered(screen, true);
+    window_key(0, "w1")
+}
+
+fn set_double_buffered(screen:&mut Headless, enabled:bool) {
+    screen.handle_text(&json!({ "type": "window-set-double-buffered", "window": "w1", "enabled": enabled }).to_string());
+}
+
+fn pixel(screen:&mut Headless, color:&str, x:i64) {
+    screen.handle_text(&json!({ "type": "MAKE_DrawPixel_name", "window": "w1", "color": color, "x": x, "y": 0 }).to_string());
+}
+
+fn commit(screen:&mut Headless) {
+    screen.handle_text(&json!({ "type": "window-commit", "window": "w1" }).to_string());
+}
+
+//the first four pixels of the top row, as shown on the screen
+fn shown(screen:&mut Headless, key:&str) -> Vec<(u8, u8, u8, u8)> {
+    let pixels = screen.window_pixels(key).unwrap();
+    (0..4).map(|x| rgba(&pixels, 8, x, 0)).collect()
+}
+
+#[test]
+fn drawing_only_shows_after_a_commit() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        pixel(screen, "red", 0);
+        assert_eq!(shown(screen, &key), [BLACK, BLACK, BLACK, BLACK]);
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, BLACK, BLACK, BLACK]);
+    });
+}
+
+#[test]
+fn the_back_buffer_keeps_the_last_frame() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        pixel(screen, "red", 0);
+        commit(screen);
+        //drawing on top of the last frame, not a blank buffer
+        pixel(screen, "lime", 1);
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, GREEN, BLACK, BLACK]);
+        //committing again with nothing new changes nothing
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, GREEN, BLACK, BLACK]);
+    });
+}
+
+#[test]
+fn the_back_buffer_starts_from_what_is_shown() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        set_double_buffered(screen, false);
+        pixel(screen, "red", 0);
+        set_double_buffered(screen, true);
+        pixel(screen, "lime", 1);
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, GREEN, BLACK, BLACK]);
+    });
+}
+
+#[test]
+fn turning_it_off_shows_what_was_pending() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        pixel(screen, "blue", 2);
+        set_double_buffered(screen, false);
+        assert_eq!(shown(screen, &key), [BLACK, BLACK, BLUE, BLACK]);
+        assert!(!screen.backend.back_buffers.contains_key(&key));
+        //and drawing goes straight to the screen again
+        pixel(screen, "red", 0);
+        assert_eq!(shown(screen, &key), [RED, BLACK, BLUE, BLACK]);
+    });
+}
+
+#[test]
+fn a_group_is_committed_where_it_says() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        screen.handle_text(&json!({
+            "type": "group-message", "category": "test", "messages": [
+                { "type": "MAKE_DrawPixel_name", "window": "w1", "color": "red", "x": 0, "y": 0 },
+                { "type": "window-commit", "window": "w1" },
+                { "type": "MAKE_DrawPixel_name", "window": "w1", "color": "lime", "x": 1, "y": 0 },
+            ],
+        }).to_string());
+        assert_eq!(shown(screen, &key), [RED, BLACK, BLACK, BLACK]);
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, GREEN, BLACK, BLACK]);
+    });
+}
+
+#[test]
+fn commits_to_single_buffered_windows_do_nothing() {
+    on_screen(|screen| {
+        let key = double_buffered_window(screen);
+        set_double_buffered(screen, false);
+        pixel(screen, "red", 0);
+        commit(screen);
+        assert_eq!(shown(screen, &key), [RED, BLACK, BLACK, BLACK]);
+        assert!(screen.check().is_ok());
+    });
+}
@@ -0,0 +1,57 @@
+//the geometry of shadows and window animations
+mod common;
+
+use std::time::{Duration, Instant};
+use rust_screen::effects::{Animation, AnimationKind, animate, shadow_layers};
+use rust_screen::window::Bounds;
+use common::{SCREEN_SIZE, xywh};
+
+const WIN:Bounds = Bounds { x: 100, y: 100, width: 200, height: 100 };
+
+#[test]
+fn open_grows_into_place() {
+    let (start, fade) = animate(AnimationKind::Open, 0.0, WIN, SCREEN_SIZE);
+    assert_eq!(xywh(start), (120, 110, 160, 80));
+    assert_eq!(fade, 0.0);
+    let (end, fade) = animate(AnimationKind::Open, 1.0, WIN, SCREEN_SIZE);
+    assert_eq!(xywh(end), xywh(WIN));
+    assert_eq!(fade, 1.0);
+}
+
+#[test]
+fn close_is_open_backwards() {
+    for &t in &[0.0, 0.25, 0.5, 1.0] {
+        let (closing, _) = animate(AnimationKind::Close, t, WIN, SCREEN_SIZE);
+        let (opening, _) = animate(AnimationKind::Open, 1.0 - t, WIN, SCREEN_SIZE);
+        assert_eq!(xywh(closing), xywh(opening));
+    }
+}
+
+#[test]
+fn minimize_heads_for_the_bottom_of_the_screen() {
+    let (end, fade) = animate(AnimationKind::Minimize, 1.0, WIN, SCREEN_SIZE);
+    assert_eq!(xywh(end), (320, 480, 0, 0));
+    assert_eq!(fade, 0.0);
+    let (back, _) = animate(AnimationKind::Restore, 1.0, WIN, SCREEN_SIZE);
+    assert_eq!(xywh(back), xywh(WIN));
+}
+
+#[test]
+fn progress_is_clamped() {
+    let animation = Animation::new(AnimationKind::Open, Duration::from_millis(100));
+    assert!(animation.progress(animation.start) < 0.01);
+    assert!(!animation.done(animation.start));
+    assert_eq!(animation.progress(animation.start + Duration::from_secs(5)), 1.0);
+    assert!(animation.done(Instant::now() + Duration::from_millis(100)));
+    //never mind zero
+    assert!(Animation::new(AnimationKind::Close, Duration::from_millis(0)).done(Instant::now()));
+}
+
+#[test]
+fn shadow_spreads_down_and_right() {
+    let layers = shadow_layers(WIN, 3);
+    assert_eq!(layers.len(), 3);
+    assert_eq!(xywh(layers[0]), (101, 101, 200, 100));
+    assert_eq!(xywh(layers[2]), (103, 103, 200, 100));
+    assert!(shadow_layers(WIN, 0).is_empty());
+}
@@ -0,0 +1,52 @@
+//a server sending more than a frame's worth of messages
+mod common;
+
+use serde_json::json;
+use rust_screen::headless::Headless;
+use rust_screen::namespace::window_key;
+use rust_screen::sdl2backend::{Processed, MESSAGES_PER_FRAME};
+use common::on_screen;
+
+fn move_to(screen:&mut Headless, x:usize) {
+    screen.queue_text(&json!({ "type": "window-set-position-request", "window": "w1", "x": x, "y": 0 }).to_string());
+}
+
+fn process(screen:&mut Headless) -> Processed {
+    screen.backend.process_render_messages(&mut screen.windows)
+}
+
+#[test]
+fn each_frame_handles_a_limited_number_of_messages() {
+    on_screen(|screen| {
+        screen.handle_text(&json!({
+            "type": "MAKE_window_list_name",
+            "windows": { "w1": { "id": "w1", "x": 0, "y": 0, "width": 8, "height": 8, "owner": "app", "window_type": "PLAIN" } },
+        }).to_string());
+        let key = window_key(0, "w1");
+        for x in 0..MESSAGES_PER_FRAME * 2 + 10 {
+            move_to(screen, x);
+        }
+        assert!(matches!(process(screen), Processed::Backlog));
+        assert_eq!(screen.windows[&key].x, MESSAGES_PER_FRAME as i32 - 1);
+        assert!(matches!(process(screen), Processed::Backlog));
+        assert_eq!(screen.windows[&key].x, MESSAGES_PER_FRAME as i32 * 2 - 1);
+        assert!(matches!(process(screen), Processed::Drained));
+        assert_eq!(screen.windows[&key].x, MESSAGES_PER_FRAME as i32 * 2 + 9);
+    });
+}
+
+#[test]
+fn pump_keeps_going_until_the_queue_is_empty() {
+    on_screen(|screen| {
+        screen.handle_text(&json!({
+            "type": "MAKE_window_list_name",
+            "windows": { "w1": { "id": "w1", "x": 0, "y": 0, "width": 8, "height": 8, "owner": "app", "window_type": "PLAIN" } },
+        }).to_string());
+        for x in 0..MESSAGES_PER_FRAME * 3 {
+            move_to(screen, x);
+        }
+        assert!(screen.pump().is_none());
+        assert_eq!(screen.windows[&window_key(0, "w1")].x, MESSAGES_PER_FRAME as i32 * 3 - 1);
+        assert!(matches!(process(screen), Processed::Drained));
+    });
+}
@@ -0,0 +1,152 @@
+//property tests for the message handling path. the same ground as the cargo-fuzz targets in fuzz/,
+//but small enough to run on every `cargo test`. everything runs against the headless renderer.
+//SDL can only be started from one thread, so all the rendering properties share a single test.
+use std::cell::RefCell;
+use proptest::prelude::*;
+use proptest::test_runner::{TestRunner, Config};
+use serde_json::{json, Value};
+use rust_screen::headless::Headless;
+use rust_screen::registry::default_registry;
+use rust_screen::binary::parse_binary;
+
+fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".{0,8}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| prop_oneof![
+        prop::collection::vec(inner.clone(), 0..8).prop_map(Value::from),
+        prop::collection::hash_map("[a-z_]{1,8}", inner, 0..8)
+            .prop_map(|m| Value::Object(m.into_iter().collect())),
+    ])
+}
+
+//a json object with a real message type, so it gets past the first check in parse_message
+fn arb_typed_json() -> impl Strategy<Value = Value> {
+    let types = default_registry().message_types();
+    (prop::sample::select(types), arb_json()).prop_map(|(t, v)| {
+        let mut obj = match v {
+            Value::Object(m) => m,
+            _ => serde_json::Map::new(),
+        };
+        obj.insert("type".to_string(), Value::from(t));
+        Value::Object(obj)
+    })
+}
+
+//mostly plausible numbers, with the extremes mixed in
+fn arb_coord() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        8 => -20i64..600,
+        1 => any::<i64>(),
+        1 => prop::sample::select(vec![i64::MIN, i64::MAX, -1, 0, 1 << 16, (1 << 16) + 1]),
+    ]
+}
+
+fn arb_window() -> impl Strategy<Value = String> {
+    prop::sample::select(vec!["w0", "w1", "w2", "missing"]).prop_map(|s| s.to_string())
+}
+
+fn arb_color() -> impl Strategy<Value = String> {
+    prop_oneof![
+        prop::sample::select(vec!["red", "black", "#ff00ff", "#zzzzzz", "#", "", "#12"]).prop_map(|s| s.to_string()),
+        ".{0,8}",
+    ]
+}
+
+fn arb_draw() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        (arb_window(), arb_color(), arb_coord(), arb_coord()).prop_map(|(w, c, x, y)| json!({
+            "type": "MAKE_DrawPixel_name", "window": w, "color": c, "x": x, "y": y,
+        })),
+        (arb_window(), arb_color(), arb_coord(), arb_coord(), arb_coord(), arb_coord()).prop_map(|(w, c, x, y, wd, ht)| json!({
+            "type": "MAKE_DrawRect_name", "window": w, "color": c, "x": x, "y": y, "width": wd, "height": ht,
+        })),
+        (arb_window(), arb_color(), arb_coord(), arb_coord(), 0i64..40, 0i64..40, prop::collection::vec(any::<u8>(), 0..2000)).prop_map(|(w, c, x, y, wd, ht, px)| json!({
+            "type": "MAKE_DrawImage_name", "window": w, "color": c, "x": x, "y": y, "width": wd, "height": ht,
+            "depth": 8, "channels": 4, "pixels": px,
+        })),
+        (arb_window(), arb_coord(), arb_coord(), arb_coord(), arb_coord(), arb_coord(), arb_coord()).prop_map(|(w, x, y, wd, ht, dx, dy)| json!({
+            "type": "window-copy-rect", "window": w, "x": x, "y": y, "width": wd, "height": ht, "dx": dx, "dy": dy,
+        })),
+        arb_window().prop_map(|w| json!({ "type": "window-commit", "window": w })),
+        (arb_window(), any::<bool>()).prop_map(|(w, e)| json!({ "type": "window-set-double-buffered", "window": w, "enabled": e })),
+    ]
+}
+
+fn arb_group_member() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        4 => arb_draw(),
+        1 => (arb_coord(), arb_coord()).prop_map(|(x, y)| json!({ "type": "group-push-translate", "x": x, "y": y })),
+        1 => Just(json!({ "type": "group-pop-translate" })),
+        1 => (arb_coord(), arb_coord(), arb_coord(), arb_coord()).prop_map(|(x, y, w, h)| json!({
+            "type": "group-push-clip", "x": x, "y": y, "width": w, "height": h,
+        })),
+        1 => Just(json!({ "type": "group-pop-clip" })),
+    ]
+}
+
+fn arb_window_list() -> impl Strategy<Value = Value> {
+    prop::collection::vec((arb_window(), arb_coord(), arb_coord()), 0..4).prop_map(|wins| {
+        let mut map = serde_json::Map::new();
+        for (id, w, h) in wins {
+            map.insert(id.clone(), json!({
+                "id": id, "x": 0, "y": 0, "width": w, "height": h, "owner": "app", "window_type": "plain",
+            }));
+        }
+        json!({ "type": "MAKE_window_list_name", "windows": map })
+    })
+}
+
+fn arb_message() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        2 => arb_window_list(),
+        4 => arb_draw(),
+        2 => prop::collection::vec(arb_group_member(), 0..16).prop_map(|m| json!({ "type": "group-message", "messages": m })),
+        1 => arb_typed_json(),
+    ]
+}
+
+#[test]
+fn parser_survives_arbitrary_input() {
+    let registry = default_registry();
+    let (send, _receive) = std::sync::mpsc::channel();
+    let mut runner = TestRunner::new(Config::with_cases(512));
+    runner.run(&prop_oneof![".*".boxed(), arb_json().prop_map(|v| v.to_string()).boxed(), arb_typed_json().prop_map(|v| v.to_string()).boxed()], |txt| {
+        let _ = rust_screen::incoming::parse_message(&registry, &send, txt);
+        Ok(())
+    }).unwrap();
+    runner.run(&prop::collection::vec(any::<u8>(), 0..256), |data| {
+        let _ = parse_binary(&data);
+        Ok(())
+    }).unwrap();
+}
+
+#[test]
+fn renderer_survives_arbitrary_messages() {
+    //the runner only takes Fn closures
+    let headless = RefCell::new(Headless::new().expect("could not start the headless renderer"));
+    let mut runner = TestRunner::new(Config::with_cases(256));
+    runner.run(&prop::collection::vec(arb_message(), 1..24), |messages| {
+        let mut headless = headless.borrow_mut();
+        headless.reset();
+        for msg in messages {
+            headless.handle_text(&msg.to_string());
+            headless.check().map_err(|e| TestCaseError::fail(e))?;
+        }
+        Ok(())
+    }).unwrap();
+    runner.run(&prop::collection::vec(any::<u8>(), 0..512), |data| {
+        let mut headless = headless.borrow_mut();
+        headless.reset();
+        headless.handle_text(r#"{"type":"MAKE_window_list_name","windows":{"w0":{"id":"w0","x":0,"y":0,"width":64,"height":64,"owner":"app","window_type":"plain"}}}"#);
+        if let Ok(msg) = parse_binary(&data) {
+            headless.apply(vec![msg]);
+        }
+        headless.check().map_err(|e| TestCaseError::fail(e))?;
+        Ok(())
+    }).unwrap();
+}
@@ -0,0 +1,165 @@
+//each DrawImage encoding decodes to the same RGBA pixels, and bad data is an error rather than a panic
+use serde_json::{json, Value};
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use rust_screen::imagedecode::{decode_draw_image, decode_qoi};
+
+//a 2x2 image, one pixel per row of bytes
+const PIXELS:[u8; 16] = [
+    10, 20, 30, 40,
+    11, 20, 29, 40,
+    17, 25, 32, 40,
+    10, 20, 30, 40,
+];
+
+//PIXELS as qoi, using each of the ops that don't start a run
+fn qoi() -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&2u32.to_be_bytes());
+    bytes.extend_from_slice(&2u32.to_be_bytes());
+    bytes.extend_from_slice(&[4, 0]);
+    bytes.extend_from_slice(&[0xFF, 10, 20, 30, 40]); //rgba
+    bytes.push(0x79); //diff +1 0 -1
+    bytes.extend_from_slice(&[0xA5, 0x96]); //luma +6 +5 +3
+    bytes.push(0x0C); //index of the first pixel
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    bytes
+}
+
+fn qoi_header(width:u32, height:u32) -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[4, 0]);
+    bytes
+}
+
+fn encoded(encoding:&str, width:i64, height:i64, data:&[u8]) -> Value {
+    json!({
+        "type": "MAKE_DrawImage_name",
+        "window": "w1",
+        "x": 5,
+        "y": 6,
+        "width": width,
+        "height": height,
+        "encoding": encoding,
+        "data": base64::encode(data),
+    })
+}
+
+fn decoded(v:&Value) -> (i64, i64, Vec<u8>) {
+    let img = decode_draw_image(v.clone()).unwrap_or_else(|e| panic!("{} didn't decode: {}", v, e));
+    assert_eq!((img.window.as_str(), img.x, img.y, img.depth, img.channels), ("w1", 5, 6, 8, 4));
+    (img.width, img.height, img.pixels)
+}
+
+#[test]
+fn array() {
+    let v = json!({
+        "type": "MAKE_DrawImage_name",
+        "window": "w1",
+        "color": "black",
+        "x": 5,
+        "y": 6,
+        "width": 2,
+        "height": 2,
+        "depth": 8,
+        "channels": 4,
+        "pixels": PIXELS.to_vec(),
+    });
+    assert_eq!(decoded(&v), (2, 2, PIXELS.to_vec()));
+}
+
+#[test]
+fn raw() {
+    assert_eq!(decoded(&encoded("raw", 2, 2, &PIXELS)), (2, 2, PIXELS.to_vec()));
+}
+
+#[test]
+fn png() {
+    let img = RgbaImage::from_raw(2, 2, PIXELS.to_vec()).unwrap();
+    let mut png:Vec<u8> = vec![];
+    DynamicImage::ImageRgba8(img).write_to(&mut png, ImageOutputFormat::Png).unwrap();
+    //the size comes from the file, not the message
+    assert_eq!(decoded(&encoded("png", 0, 0, &png)), (2, 2, PIXELS.to_vec()));
+}
+
+#[test]
+fn qoi_ops() {
+    assert_eq!(decode_qoi(&qoi()).unwrap(), (2, 2, PIXELS.to_vec()));
+    assert_eq!(decoded(&encoded("qoi", 0, 0, &qoi())), (2, 2, PIXELS.to_vec()));
+}
+
+#[test]
+fn qoi_runs_and_rgb() {
+    let mut bytes = qoi_header(3, 1);
+    bytes.extend_from_slice(&[0xFE, 1, 2, 3]); //rgb keeps the starting alpha of 255
+    bytes.push(0xC1); //a run of 2
+    assert_eq!(decode_qoi(&bytes).unwrap(), (3, 1, [1u8, 2, 3, 255].repeat(3)));
+}
+
+#[test]
+fn indexed() {
+    let palette:Vec<u8> = PIXELS[0..12].to_vec();
+    let mut v = encoded("indexed", 2, 2, &[0, 1, 2, 0]);
+    v["palette"] = json!(palette);
+    assert_eq!(decoded(&v), (2, 2, PIXELS.to_vec()));
+}
+
+#[test]
+fn bad_qoi_is_an_error() {
+    let whole = qoi();
+    for cut in &[0, 4, 13, 14, 18, 21] {
+        assert!(decode_qoi(&whole[..*cut]).is_err(), "qoi cut to {} bytes should not decode", cut);
+    }
+    let mut magic = whole.clone();
+    magic[0] = b'Q';
+    assert!(decode_qoi(&magic).is_err());
+    //sizes that overflow, or would allocate far too much, are refused before reading any pixels
+    assert!(decode_qoi(&qoi_header(0xFFFF_FFFF, 0xFFFF_FFFF)).is_err());
+    assert!(decode_qoi(&qoi_header(100_000, 100_000)).is_err());
+    assert!(decode_qoi(&qoi_header(0, 10)).is_err());
+    assert!(decode_draw_image(encoded("qoi", 2, 2, &whole[..18])).is_err());
+}
+
+#[test]
+fn bad_raw_is_an_error() {
+    assert!(decode_draw_image(encoded("raw", 2, 2, &PIXELS[..15])).is_err());
+    assert!(decode_draw_image(encoded("raw", 2, 3, &PIXELS)).is_err());
+    assert!(decode_draw_image(encoded("raw", 0, 2, &[])).is_err());
+    assert!(decode_draw_image(encoded("raw", -2, -2, &PIXELS)).is_err());
+    assert!(decode_draw_image(encoded("raw", i64::MAX, 2, &PIXELS)).is_err());
+    assert!(decode_draw_image(encoded("raw", 1 << 32, 1 << 32, &[])).is_err());
+}
+
+#[test]
+fn bad_indexed_is_an_error() {
+    let palette = json!([0, 0, 0, 255, 255, 255, 255, 255]);
+    let mut out_of_range = encoded("indexed", 2, 1, &[1, 2]);
+    out_of_range["palette"] = palette.clone();
+    assert!(decode_draw_image(out_of_range).is_err());
+    let mut short = encoded("indexed", 2, 2, &[0, 1, 0]);
+    short["palette"] = palette.clone();
+    assert!(decode_draw_image(short).is_err());
+    let mut overflow = encoded("indexed", i64::MAX, i64::MAX, &[0]);
+    overflow["palette"] = palette.clone();
+    assert!(decode_draw_image(overflow).is_err());
+    let mut not_bytes = encoded("indexed", 1, 1, &[0]);
+    not_bytes["palette"] = json!([0, 0, 0, 256]);
+    assert!(decode_draw_image(not_bytes).is_err());
+    assert!(decode_draw_image(encoded("indexed", 1, 1, &[0])).is_err());
+}
+
+#[test]
+fn bad_payloads_are_errors() {
+    assert!(decode_draw_image(encoded("png", 2, 2, b"not a png")).is_err());
+    assert!(decode_draw_image(encoded("bmp", 2, 2, &PIXELS)).is_err());
+    let mut not_base64 = encoded("raw", 2, 2, &PIXELS);
+    not_base64["data"] = json!("!!!");
+    assert!(decode_draw_image(not_base64).is_err());
+    let mut missing = encoded("raw", 2, 2, &PIXELS);
+    missing.as_object_mut().unwrap().remove("data");
+    assert!(decode_draw_image(missing).is_err());
+    let mut not_a_string = encoded("raw", 2, 2, &PIXELS);
+    not_a_string["encoding"] = json!(3);
+    assert!(decode_draw_image(not_a_string).is_err());
+}
@@ -0,0 +1,13 @@
+//what each log level lets through
+use rust_screen::config::LogLevel;
+use rust_screen::logging::{set_level, enabled};
+
+#[test]
+fn each_level_includes_the_ones_below_it() {
+    set_level(LogLevel::Quiet);
+    assert!(!enabled(LogLevel::Info) && !enabled(LogLevel::Debug));
+    set_level(LogLevel::Info);
+    assert!(enabled(LogLevel::Info) && !enabled(LogLevel::Debug));
+    set_level(LogLevel::Debug);
+    assert!(enabled(LogLevel::Info) && enabled(LogLevel::Debug));
+}
@@ -0,0 +1,373 @@
+//end to end tests against the mock server, over a real websocket on localhost.
+//SDL can only be started once, so the tests that need a screen take turns on one, see common::on_screen
+mod common;
+
+use std::io::Read;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::json;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+use idealos_schemas::general::ScreenStart_name;
+use idealos_schemas::windows::WindowOpenDisplay_name;
+use idealos_schemas::graphics::DrawRect_name;
+use rust_screen::connection::{Network, ServerConnection, start_network, PROTOCOL_VERSION};
+use rust_screen::headless::Headless;
+use rust_screen::heartbeat::Heartbeat;
+use rust_screen::messages::{RenderMessage, ScreenStop_message, MouseDown_name, ScreenCapabilities_message, SetScreenSize_message, WindowCloseRequest_message, DesktopSetWallpaper_message, WindowSetOpacity_message, WindowSetMinimized_message, WindowSetScale_message, WindowSetPosition_message, WindowSetSize_message};
+use rust_screen::mockserver::MockServer;
+use rust_screen::namespace::window_key;
+use rust_screen::registry::default_registry;
+use rust_screen::tls::ConnectOptions;
+use rust_screen::theme::{Theme, ThemeConfig};
+use rust_screen::desktop::WallpaperMode;
+use rust_screen::window::Bounds;
+use common::{on_screen, xywh};
+use sdl2::mouse::MouseButton;
+
+const WAIT:Duration = Duration::from_secs(5);
+
+//just the network side, with the render loop's end of the channels handed back to the test
+fn connect(server:&MockServer) -> (Receiver<RenderMessage>, UnboundedSender<Message>, Network) {
+    connect_with_heartbeat(&server.url(), Heartbeat::new(Duration::from_secs(5), Duration::from_secs(15)))
+}
+
+fn connect_with_heartbeat(url:&str, heartbeat:Heartbeat) -> (Receiver<RenderMessage>, UnboundedSender<Message>, Network) {
+    let (render_loop_send, render) = channel();
+    let (server_out, server_out_send) = unbounded_channel::<Message>();
+    let connection = ServerConnection {
+        name: url.to_string(),
+        heartbeat: Arc::new(heartbeat),
+        server_out_send,
+        render_loop_send,
+    };
+    let network = start_network(vec![connection], ConnectOptions { ca_cert: None, pin_cert: None, token: None },
+                                Arc::new(default_registry()), 640, 480, 1.5);
+    (render, server_out, network)
+}
+
+//what reaches the render loop, up to and including the first message the check passes
+fn receive_until<F>(render:&Receiver<RenderMessage>, check:F) -> Vec<RenderMessage> where F: Fn(&RenderMessage) -> bool {
+    let mut got = vec![];
+    loop {
+        let msg = render.recv_timeout(WAIT).expect("the render loop wasn't sent what it waited for");
+        let done = check(&msg);
+        got.push(msg);
+        if done { return got; }
+    }
+}
+
+fn binary_pixel(window:&str, x:i32, y:i32) -> Vec<u8> {
+    let mut frame = vec![0x01];
+    frame.extend_from_slice(&(window.len() as u16).to_le_bytes());
+    frame.extend_from_slice(window.as_bytes());
+    frame.extend_from_slice(&x.to_le_bytes());
+    frame.extend_from_slice(&y.to_le_bytes());
+    frame.extend_from_slice(&[0, 0, 0, 255]);
+    frame
+}
+
+fn is_pixel_at(msg:&RenderMessage, x:i64) -> bool {
+    matches!(msg, RenderMessage::DrawPixel(m) if m.x == x)
+}
+
+#[test]
+fn screen_says_hello() {
+    let server = MockServer::start().unwrap();
+    let (_render, server_out, network) = connect(&server);
+
+    let start = server.wait_for(ScreenStart_name, WAIT).unwrap();
+    assert_eq!(start["binary_protocol"], 1);
+    let caps = server.wait_for(ScreenCapabilities_message, WAIT).unwrap();
+    assert_eq!(caps["protocol_version"], PROTOCOL_VERSION);
+    assert_eq!(caps["scale"], 1.5);
+    assert_eq!(caps["color_depth"], 32);
+    assert_eq!(caps["client_name"], "rust-screen");
+    assert!(caps["message_types"].as_array().unwrap().iter().any(|t| t == WindowOpenDisplay_name));
+    assert!(caps["image_encodings"].as_array().unwrap().iter().any(|e| e == "qoi"));
+    assert!(caps["input_events"].as_array().unwrap().iter().any(|e| e == MouseDown_name));
+    let size = server.wait_for(SetScreenSize_message, WAIT).unwrap();
+    assert_eq!(size["width"], 640);
+    assert_eq!(size["height"], 480);
+    assert!(network.was_connected());
+    network.shutdown(&[server_out], WAIT);
+}
+
+#[test]
+fn a_server_that_stops_answering_is_disconnected() {
+    let server = MockServer::start().unwrap();
+    let (render, server_out, network) = connect_with_heartbeat(&server.url(),
+        Heartbeat::new(Duration::from_millis(50), Duration::from_millis(300)));
+    receive_until(&render, |m| matches!(m, RenderMessage::ServerCapabilities(_)));
+
+    //answered pings keep an otherwise quiet connection up past the timeout
+    let quiet = Duration::from_millis(600);
+    assert!(!render.recv_timeout(quiet).map_or(false, |m| matches!(m, RenderMessage::Disconnected(_))));
+
+    server.hang();
+    let got = receive_until(&render, |m| matches!(m, RenderMessage::Disconnected(_)));
+    match got.last() {
+        Some(RenderMessage::Disconnected(reason)) => assert!(reason.contains("stopped answering"), "{}", reason),
+        _ => unreachable!(),
+    }
+    network.shutdown(&[server_out], WAIT);
+}
+
+#[test]
+fn shutdown_says_goodbye() {
+    let server = MockServer::start().unwrap();
+    let (render, server_out, network) = connect(&server);
+    receive_until(&render, |m| matches!(m, RenderMessage::ServerCapabilities(_)));
+    let started = Instant::now();
+    network.shutdown(&[server_out], WAIT);
+    assert!(started.elapsed() < WAIT);
+    assert!(server.wait_for(ScreenStop_message, WAIT).is_some());
+}
+
+#[test]
+fn shutdown_drops_connections_that_dont_finish() {
+    //takes the connection but never answers the websocket handshake
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (_render, server_out, network) = connect_with_heartbeat(&url, Heartbeat::new(Duration::from_secs(5), Duration::from_secs(15)));
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let started = Instant::now();
+    network.shutdown(&[server_out], Duration::from_millis(200));
+    assert!(started.elapsed() < WAIT);
+
+    //the screen's end was closed, not left open: the handshake request, then the end of the stream
+    stream.set_read_timeout(Some(WAIT)).unwrap();
+    let mut buf = vec![0; 4096];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => continue,
+            Err(e) => panic!("the connection is still open: {}", e),
+        }
+    }
+}
+
+#[test]
+fn binary_frames_need_the_servers_agreement() {
+    for (caps, binary) in vec![(json!({ "binary_protocol": 1 }), true), (json!({ "protocol_version": 2 }), false)] {
+        let server = MockServer::start_with_capabilities(caps).unwrap();
+        let (render, server_out, network) = connect(&server);
+        receive_until(&render, |m| matches!(m, RenderMessage::ServerCapabilities(_)));
+        server.send_binary(binary_pixel("win1", 1, 0));
+        server.draw_pixel("win1", "black", 2, 0);
+        let got = receive_until(&render, |m| is_pixel_at(m, 2));
+        assert_eq!(got.iter().any(|m| is_pixel_at(m, 1)), binary);
+        network.shutdown(&[server_out], WAIT);
+    }
+}
+
+#[test]
+fn image_encodings_need_the_servers_agreement() {
+    let server = MockServer::start_with_capabilities(json!({ "image_encodings": ["raw"] })).unwrap();
+    let (render, server_out, network) = connect(&server);
+    receive_until(&render, |m| matches!(m, RenderMessage::ServerCapabilities(_)));
+    let image = |encoding:&str, data:&[u8]| json!({
+        "type": "MAKE_DrawImage_name", "window": "win1", "x": 0, "y": 0, "width": 1, "height": 1,
+        "encoding": encoding, "data": base64::encode(data),
+    });
+    let mut qoi = b"qoif".to_vec();
+    qoi.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1, 4, 0, 0xFF, 1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]);
+    server.send(image("qoi", &qoi));
+    server.send(image("raw", &[1, 2, 3, 4]));
+    server.group(vec![image("qoi", &qoi)]);
+    server.draw_pixel("win1", "black", 2, 0);
+    let got = receive_until(&render, |m| is_pixel_at(m, 2));
+    let images = got.iter().filter(|m| matches!(m, RenderMessage::DrawImage(_))).count();
+    assert_eq!(images, 1);
+    let group_images = got.iter().map(|m| match m {
+        RenderMessage::Group(g) => g.messages.len(),
+        _ => 0,
+    }).sum::<usize>();
+    assert_eq!(group_images, 0);
+    network.shutdown(&[server_out], WAIT);
+}
+
+//the shared headless screen, connected to a new mock server which has opened win1 at 50,60 100x80.
+//the test gets the screen, the server and the window's key
+fn with_window<F>(test:F) where F: FnOnce(&mut Headless, &MockServer, &str) + Send + 'static {
+    on_screen(move |screen| {
+        let server = MockServer::start().unwrap();
+        let network = screen.connect_to(&[server.url()]);
+        let key = window_key(0, "win1");
+        server.open_window("win1", 50, 60, 100, 80);
+        assert!(screen.pump_until(WAIT, |s| s.windows.contains_key(&key)));
+        test(screen, &server, &key);
+        network.shutdown(&screen.server_outs(), WAIT);
+    });
+}
+
+#[test]
+fn windows_open_and_clicks_come_back() {
+    with_window(|screen, server, key| {
+        let win = &screen.windows[key];
+        assert_eq!(xywh(win.bounds()), (50, 60, 100, 80));
+
+        //drawing into it, in a group, doesn't produce any errors
+        server.group(vec![
+            json!({ "type": DrawRect_name, "window": "win1", "color": "red", "x": 0, "y": 0, "width": 10, "height": 10 }),
+        ]);
+        server.draw_pixel("win1", "black", 5, 5);
+
+        //clicks come back in window coordinates, with the server's window id
+        screen.click(60, 75);
+        let down = server.wait_for(MouseDown_name, WAIT).unwrap();
+        assert_eq!(down["window"], "win1");
+        assert_eq!(down["x"], 10);
+        assert_eq!(down["y"], 15);
+        assert!(server.received_of_type("screen-error").is_empty());
+    });
+}
+
+#[test]
+fn opacity_is_sent_from_0_to_1() {
+    with_window(|screen, server, key| {
+        //and kept out of 255
+        server.send(json!({ "type": WindowSetOpacity_message, "window": "win1", "opacity": 0.5 }));
+        assert!(screen.pump_until(WAIT, |s| s.windows[key].opacity == 128));
+        server.send(json!({ "type": WindowSetOpacity_message, "window": "win1", "opacity": 2.0 }));
+        assert!(screen.pump_until(WAIT, |_| !server.received_of_type("screen-error").is_empty()));
+        assert_eq!(screen.windows[key].opacity, 128);
+    });
+}
+
+#[test]
+fn magnified_windows_get_input_in_their_own_pixels() {
+    with_window(|screen, server, key| {
+        server.send(json!({ "type": WindowSetScale_message, "window": "win1", "scale": 2 }));
+        assert!(screen.pump_until(WAIT, |s| s.windows[key].scale == 2));
+        screen.click(71, 91);
+        let down = server.wait_for(MouseDown_name, WAIT).unwrap();
+        assert_eq!((down["x"].as_i64(), down["y"].as_i64()), (Some(10), Some(15)));
+    });
+}
+
+#[test]
+fn minimized_windows_are_kept_until_they_are_restored() {
+    with_window(|screen, server, key| {
+        server.send(json!({ "type": WindowSetMinimized_message, "window": "win1", "minimized": true }));
+        assert!(screen.pump_until(WAIT, |s| s.windows[key].minimized));
+        //not drawn or clicked
+        screen.draw();
+        screen.click(60, 75);
+        server.send(json!({ "type": WindowSetMinimized_message, "window": "win1", "minimized": false }));
+        assert!(screen.pump_until(WAIT, |s| !s.windows[key].minimized));
+        screen.click(61, 75);
+        let down = server.wait_for(MouseDown_name, WAIT).unwrap();
+        assert_eq!(down["x"], 11);
+    });
+}
+
+#[test]
+fn the_close_button_asks_the_app_to_close_the_window() {
+    with_window(|screen, server, _| {
+        let theme:ThemeConfig = toml::from_str("[title]\nclose_glyph = 120").unwrap();
+        screen.backend.set_theme(Theme::from_config(&theme, Path::new(".")).unwrap()).unwrap();
+        screen.click(145, 55);
+        let close = server.wait_for(WindowCloseRequest_message, WAIT).unwrap();
+        assert_eq!(close["window"], "win1");
+        assert_eq!(close["target"], "mock-app");
+    });
+}
+
+#[test]
+fn a_desktop_app_can_set_the_wallpaper() {
+    with_window(|screen, server, _| {
+        server.send(json!({
+            "type": DesktopSetWallpaper_message, "color": "teal", "mode": "tiled",
+            "image": { "encoding": "raw", "width": 1, "height": 1, "data": base64::encode(&[0u8, 0, 0, 255]) },
+        }));
+        assert!(screen.pump_until(WAIT, |s| s.backend.desktop.mode == WallpaperMode::Tiled));
+        assert!(screen.backend.desktop.image.is_some());
+    });
+}
+
+//two monitors side by side
+fn two_monitors(screen:&mut Headless) {
+    screen.backend.monitors = vec![
+        Bounds { x: 0, y: 0, width: 256, height: 320 },
+        Bounds { x: 256, y: 0, width: 256, height: 320 },
+    ];
+}
+
+#[test]
+fn maximizing_fills_the_monitor_the_window_is_on() {
+    with_window(|screen, server, key| {
+        two_monitors(screen);
+        screen.backend.toggle_maximize(&mut screen.windows, key);
+        assert_eq!(xywh(screen.windows[key].bounds()), (1, 10, 254, 309));
+        let size = server.wait_for(WindowSetSize_message, WAIT).unwrap();
+        assert_eq!((size["width"].as_i64(), size["height"].as_i64()), (Some(254), Some(309)));
+        screen.backend.toggle_maximize(&mut screen.windows, key);
+        assert_eq!(xywh(screen.windows[key].bounds()), (50, 60, 100, 80));
+        let size = server.wait_for_nth(WindowSetSize_message, 1, WAIT).unwrap();
+        assert_eq!((size["width"].as_i64(), size["height"].as_i64()), (Some(100), Some(80)));
+    });
+}
+
+#[test]
+fn dragging_to_a_monitor_edge_snaps_the_window() {
+    with_window(|screen, _, key| {
+        two_monitors(screen);
+        //by the title bar to the right edge of the second monitor snaps it to that half
+        screen.backend.process_mousedown(60, 55, MouseButton::Left, &mut screen.windows);
+        screen.backend.process_mouseup(510, 150, MouseButton::Left, &mut screen.windows);
+        assert_eq!(xywh(screen.windows[key].bounds()), (385, 10, 126, 309));
+        //and maximize puts it back where it was before the drag
+        screen.backend.toggle_maximize(&mut screen.windows, key);
+        assert_eq!(xywh(screen.windows[key].bounds()), (50, 60, 100, 80));
+    });
+}
+
+#[test]
+fn shrinking_the_screen_pulls_windows_back_on() {
+    with_window(|screen, server, key| {
+        //and tells the server, and the window keeps its frame on the screen too
+        screen.backend.set_screen_size(&mut screen.windows, (120, 100));
+        let size = server.wait_for_nth(SetScreenSize_message, 1, WAIT).unwrap();
+        assert_eq!((size["width"].as_i64(), size["height"].as_i64()), (Some(120), Some(100)));
+        let win = &screen.windows[key];
+        assert_eq!((win.x, win.y), (19, 19));
+        let moved = server.wait_for(WindowSetPosition_message, WAIT).unwrap();
+        assert_eq!((moved["x"].as_i64(), moved["y"].as_i64()), (Some(19), Some(19)));
+    });
+}
+
+#[test]
+fn closed_windows_fade_out() {
+    with_window(|screen, server, key| {
+        server.close_window("win1");
+        assert!(screen.pump_until(WAIT, |s| !s.windows.contains_key(key)));
+        //still drawn while it fades
+        assert_eq!(screen.backend.closing.len(), 1);
+        screen.draw_at(Instant::now());
+        assert_eq!(screen.backend.closing.len(), 1);
+        //and gone once the animation is over
+        screen.draw_at(Instant::now() + Duration::from_secs(5));
+        assert!(screen.backend.closing.is_empty());
+    });
+}
+
+#[test]
+fn the_server_going_away_ends_the_session() {
+    with_window(|screen, server, _| {
+        server.disconnect();
+        let mut reason = None;
+        for _ in 0..500 {
+            reason = screen.pump();
+            if reason.is_some() { break; }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(reason.is_some());
+        assert!(screen.windows.is_empty());
+    });
+}
@@ -0,0 +1,58 @@
+//monitor geometry for a screen spanning several displays, and where windows maximize and snap to
+mod common;
+
+use sdl2::rect::Rect;
+use rust_screen::monitors::{span, monitor_bounds, monitor_at, fill, snap_area};
+use rust_screen::window::{Bounds, Insets, Point};
+use common::xywh;
+
+//a laptop with a bigger display to its left and slightly higher up, like xrandr might report
+fn displays() -> Vec<Rect> {
+    vec![Rect::new(1920, 200, 1366, 768), Rect::new(0, 0, 1920, 1080)]
+}
+
+#[test]
+fn span_covers_every_display() {
+    assert_eq!(span(&displays()), Some(Rect::new(0, 0, 3286, 1080)));
+    assert_eq!(span(&[]), None);
+}
+
+#[test]
+fn monitors_are_relative_to_the_spanning_window() {
+    let monitors = monitor_bounds(&displays(), (0, 0), 1.0);
+    assert_eq!(xywh(monitors[0]), (1920, 200, 1366, 768));
+    assert_eq!(xywh(monitors[1]), (0, 0, 1920, 1080));
+    //at a fractional scale neighbours still meet without a gap
+    let monitors = monitor_bounds(&displays(), (0, 0), 1.5);
+    assert_eq!(monitors[1].x + monitors[1].width, monitors[0].x);
+    assert_eq!(xywh(monitors[1]), (0, 0, 1280, 720));
+}
+
+#[test]
+fn points_find_their_monitor() {
+    let monitors = monitor_bounds(&displays(), (0, 0), 1.0);
+    assert_eq!(monitor_at(&monitors, &Point { x: 2000, y: 300 }).map(xywh), Some((1920, 200, 1366, 768)));
+    assert_eq!(monitor_at(&monitors, &Point { x: 100, y: 100 }).map(xywh), Some((0, 0, 1920, 1080)));
+    //in the dead area above the laptop, the nearest one wins
+    assert_eq!(monitor_at(&monitors, &Point { x: 2500, y: 50 }).map(xywh), Some((1920, 200, 1366, 768)));
+    assert!(monitor_at(&[], &Point { x: 0, y: 0 }).is_none());
+}
+
+#[test]
+fn maximized_windows_keep_their_frame_on_the_monitor() {
+    let monitor = Bounds { x: 1920, y: 200, width: 1366, height: 768 };
+    let frame = Insets { left: 1, right: 1, top: 10, bottom: 1 };
+    assert_eq!(xywh(fill(&monitor, &frame)), (1921, 210, 1364, 757));
+}
+
+#[test]
+fn snapping_uses_the_monitor_edges() {
+    let monitor = Bounds { x: 1920, y: 200, width: 1366, height: 768 };
+    let left = snap_area(&monitor, &Point { x: 1922, y: 500 }).unwrap();
+    assert_eq!(xywh(left), (1920, 200, 683, 768));
+    let right = snap_area(&monitor, &Point { x: 3280, y: 500 }).unwrap();
+    assert_eq!(xywh(right), (2603, 200, 683, 768));
+    let top = snap_area(&monitor, &Point { x: 2500, y: 203 }).unwrap();
+    assert_eq!(xywh(top), xywh(monitor));
+    assert!(snap_area(&monitor, &Point { x: 2500, y: 500 }).is_none());
+}
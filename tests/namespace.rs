@@ -0,0 +1,82 @@
+//window ids from different servers kept apart by window keys
+use rust_screen::messages::{RenderMessage, WindowSetPositionRequest, WindowSetPositionRequest_message, group_message, any_graphics_message};
+use rust_screen::namespace::{window_key, remote_id, namespace_message};
+
+#[test]
+fn keys_round_trip() {
+    assert_eq!(window_key(0, "w1"), "0/w1");
+    assert_eq!(window_key(12, "w1"), "12/w1");
+    assert_eq!(remote_id(&window_key(3, "w1")), "w1");
+    assert_eq!(remote_id(&window_key(3, "")), "");
+    //not a key at all
+    assert_eq!(remote_id("w1"), "w1");
+}
+
+#[test]
+fn ids_may_contain_slashes() {
+    for id in &["a/b", "/", "a//b/", "/leading"] {
+        let key = window_key(7, id);
+        assert_eq!(remote_id(&key), *id);
+        assert!(key.starts_with("7/"));
+    }
+    //the same id from two servers gives two keys
+    assert_ne!(window_key(1, "a/b"), window_key(2, "a/b"));
+    //and ids that look like keys don't collide with real ones
+    assert_ne!(window_key(1, "2/w"), window_key(2, "w"));
+}
+
+#[test]
+fn messages_are_keyed_by_their_connection() {
+    let mut msg = RenderMessage::WindowSetPosition(WindowSetPositionRequest {
+        type_: WindowSetPositionRequest_message.to_string(),
+        window: "a/b".to_string(),
+        x: 1,
+        y: 2,
+    });
+    namespace_message(&mut msg, 4);
+    match &msg {
+        RenderMessage::WindowSetPosition(m) => {
+            assert_eq!(m.window, "4/a/b");
+            assert_eq!(remote_id(&m.window), "a/b");
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn group_members_are_keyed_too() {
+    let mut msg = RenderMessage::Group(group_message {
+        type_: "group".to_string(),
+        category: "test".to_string(),
+        messages: vec![
+            serde_json::from_value::<any_graphics_message>(serde_json::json!({
+                "type": "MAKE_DrawPixel_name", "window": "w/1", "color": "red", "x": 0, "y": 0,
+            })).unwrap(),
+        ],
+    });
+    namespace_message(&mut msg, 2);
+    match &msg {
+        RenderMessage::Group(g) => match &g.messages[0] {
+            any_graphics_message::MAKE_DrawPixel_name { window, .. } => assert_eq!(window, "2/w/1"),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn window_lists_are_rekeyed() {
+    let mut msg = RenderMessage::WindowList(serde_json::from_value(serde_json::json!({
+        "type": "MAKE_window_list_name",
+        "windows": { "x/y": { "id": "x/y", "x": 0, "y": 0, "width": 8, "height": 8, "owner": "app", "window_type": "PLAIN" } },
+    })).unwrap());
+    namespace_message(&mut msg, 1);
+    match &msg {
+        RenderMessage::WindowList(m) => {
+            let keys:Vec<&String> = m.windows.keys().collect();
+            assert_eq!(keys, vec!["1/x/y"]);
+            assert_eq!(m.windows["1/x/y"].id, "1/x/y");
+        }
+        _ => unreachable!(),
+    }
+}
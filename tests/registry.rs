@@ -0,0 +1,76 @@
+//dispatching message text to the handler registered for its type
+use serde_json::json;
+use idealos_schemas::graphics::DrawPixel_name;
+use rust_screen::connection::Negotiated;
+use rust_screen::error::ClientError;
+use rust_screen::messages::RenderMessage;
+use rust_screen::registry::{MessageRegistry, default_registry};
+
+fn parse(registry:&MessageRegistry, txt:&str) -> Result<Option<RenderMessage>, ClientError> {
+    registry.parse(txt, &Negotiated::everything())
+}
+
+#[test]
+fn known_types_reach_their_handler() {
+    let registry = default_registry();
+    let txt = json!({ "type": DrawPixel_name, "window": "w1", "color": "red", "x": 3, "y": 4 }).to_string();
+    match parse(&registry, &txt) {
+        Ok(Some(RenderMessage::DrawPixel(m))) => assert_eq!((m.window.as_str(), m.x, m.y), ("w1", 3, 4)),
+        _ => panic!("not a pixel"),
+    }
+    //escaped type names still match
+    let first = DrawPixel_name.chars().next().unwrap();
+    let escaped = txt.replacen(DrawPixel_name, &format!("\\u{:04x}{}", first as u32, &DrawPixel_name[1..]), 1);
+    assert_ne!(escaped, txt);
+    assert!(matches!(parse(&registry, &escaped), Ok(Some(RenderMessage::DrawPixel(_)))));
+}
+
+#[test]
+fn unknown_types_are_skipped() {
+    let registry = default_registry();
+    assert!(matches!(parse(&registry, r#"{"type":"some-new-message","x":1}"#), Ok(None)));
+}
+
+#[test]
+fn messages_without_a_type_are_invalid() {
+    let registry = default_registry();
+    assert!(matches!(parse(&registry, r#"{"x":1}"#), Err(ClientError::Invalid { .. })));
+    assert!(matches!(parse(&registry, r#"{"type":7}"#), Err(ClientError::Parse(_))));
+    assert!(matches!(parse(&registry, "[1, 2]"), Err(ClientError::Parse(_))));
+    assert!(matches!(parse(&registry, "not json"), Err(ClientError::Parse(_))));
+}
+
+#[test]
+fn bad_payloads_are_parse_errors() {
+    let registry = default_registry();
+    let missing = json!({ "type": DrawPixel_name, "window": "w1", "color": "red", "x": 3 }).to_string();
+    assert!(matches!(parse(&registry, &missing), Err(ClientError::Parse(_))));
+    let wrong = json!({ "type": DrawPixel_name, "window": "w1", "color": "red", "x": "3", "y": 4 }).to_string();
+    assert!(matches!(parse(&registry, &wrong), Err(ClientError::Parse(_))));
+    let truncated = json!({ "type": DrawPixel_name, "window": "w1", "color": "red", "x": 3, "y": 4 }).to_string();
+    assert!(matches!(parse(&registry, &truncated[..truncated.len() - 1]), Err(ClientError::Parse(_))));
+}
+
+#[test]
+fn registering_a_type_again_replaces_the_handler() {
+    let mut registry = default_registry();
+    let types = registry.message_types().len();
+    registry.register(DrawPixel_name, |v, _| Ok(Some(RenderMessage::Disconnected(v.to_string()))));
+    assert_eq!(registry.message_types().len(), types);
+    let txt = json!({ "type": DrawPixel_name, "anything": true }).to_string();
+    match parse(&registry, &txt) {
+        Ok(Some(RenderMessage::Disconnected(seen))) => assert_eq!(seen, txt),
+        _ => panic!("the old handler ran"),
+    }
+}
+
+#[test]
+fn message_types_are_reported_sorted() {
+    let mut registry = MessageRegistry::new();
+    assert!(registry.message_types().is_empty());
+    registry.register_typed("b-type", |m:serde_json::Value| RenderMessage::Disconnected(m.to_string()));
+    registry.register_typed("a-type", |m:serde_json::Value| RenderMessage::Disconnected(m.to_string()));
+    assert_eq!(registry.message_types(), vec!["a-type", "b-type"]);
+    assert!(registry.handles("a-type"));
+    assert!(!registry.handles("c-type"));
+}
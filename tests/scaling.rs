@@ -0,0 +1,48 @@
+//converting between virtual and real pixels at whole and fractional scales
+use rust_screen::scaling::{scale_for_dpi, virtual_size, real_size, to_virtual, check_scale};
+
+#[test]
+fn dpi_picks_a_scale_in_halves() {
+    assert_eq!(scale_for_dpi(96.0), 1.0);
+    assert_eq!(scale_for_dpi(144.0), 1.5);
+    assert_eq!(scale_for_dpi(192.0), 2.0);
+    assert_eq!(scale_for_dpi(220.0), 2.5);
+    //low dpi displays, or ones that don't know, stay at 1
+    assert_eq!(scale_for_dpi(72.0), 1.0);
+    assert_eq!(scale_for_dpi(0.0), 1.0);
+    assert_eq!(scale_for_dpi(f32::NAN), 1.0);
+}
+
+#[test]
+fn fullscreen_keeps_the_edge_pixels() {
+    //1366 isn't a multiple of 2 or 3, so the last virtual pixel is partly off the display
+    assert_eq!(virtual_size((1366, 768), 2.0), (683, 384));
+    assert_eq!(virtual_size((1366, 768), 3.0), (456, 256));
+    assert_eq!(virtual_size((1920, 1080), 1.5), (1280, 720));
+}
+
+#[test]
+fn windowed_size_rounds_to_real_pixels() {
+    assert_eq!(real_size((512, 320), 1.0), (512, 320));
+    assert_eq!(real_size((512, 320), 1.5), (768, 480));
+    assert_eq!(real_size((101, 101), 1.5), (152, 152));
+}
+
+#[test]
+fn input_lands_in_the_right_virtual_pixel() {
+    assert_eq!(to_virtual(0.0, 1.5), 0);
+    assert_eq!(to_virtual(1.0, 1.5), 0);
+    assert_eq!(to_virtual(2.0, 1.5), 1);
+    assert_eq!(to_virtual(3.0, 1.5), 2);
+    assert_eq!(to_virtual(599.0, 2.0), 299);
+    //off the left edge while dragging
+    assert_eq!(to_virtual(-1.0, 2.0), -1);
+}
+
+#[test]
+fn scale_must_be_positive() {
+    assert!(check_scale(0.0).is_err());
+    assert!(check_scale(-1.0).is_err());
+    assert!(check_scale(f32::INFINITY).is_err());
+    assert_eq!(check_scale(1.25), Ok(1.25));
+}
@@ -0,0 +1,93 @@
+//themes: parsing, the decoration geometry hit testing relies on, and reloading
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use rust_screen::theme::{Theme, ThemeConfig, ThemeWatcher};
+use rust_screen::window::Point;
+use common::window;
+
+fn theme(text:&str) -> Result<Theme, String> {
+    let config:ThemeConfig = toml::from_str(text).map_err(|e|e.to_string())?;
+    Theme::from_config(&config, std::path::Path::new("."))
+}
+
+#[test]
+fn default_theme_matches_the_old_decorations() {
+    let theme = Theme::default();
+    let frame = theme.frame();
+    assert_eq!((frame.left, frame.right, frame.top, frame.bottom), (1, 1, 10, 1));
+    assert!(theme.close_button(&window()).is_none());
+    let outer = theme.outer(&window());
+    assert_eq!((outer.x, outer.y, outer.width, outer.height), (49, 50, 102, 91));
+}
+
+#[test]
+fn hit_testing_follows_the_theme() {
+    let theme = theme(r#"
+[border]
+left = 4
+right = 4
+top = 2
+bottom = 4
+[title]
+height = 16
+close_glyph = 120
+"#).unwrap();
+    let win = window();
+    let frame = theme.frame();
+    assert_eq!(frame.top, 18);
+    assert!(win.border_contains(&Point { x: 47, y: 43 }, &frame));
+    assert!(!win.border_contains(&Point { x: 47, y: 41 }, &frame));
+    assert!(!win.border_contains(&Point { x: 45, y: 60 }, &frame));
+    let close = theme.close_button(&win).unwrap();
+    assert!(close.contains(&Point { x: 145, y: 50 }));
+    assert!(!close.contains(&Point { x: 120, y: 50 }));
+    assert!(!close.contains(&Point { x: 145, y: 65 }));
+}
+
+#[test]
+fn bad_themes_are_rejected() {
+    assert!(theme("[border]\nleft = -1").is_err());
+    assert!(theme("[title]\ncolour = \"red\"").is_err());
+    assert!(theme("[desktop]\nbackground = \"#nothex\"").is_err());
+    assert!(theme("[desktop]\nimage = \"no/such/image.png\"").is_err());
+    assert!(theme("[effects]\nshadow_size = -2").is_err());
+}
+
+#[test]
+fn watcher_picks_up_changes() {
+    let dir = std::env::temp_dir().join(format!("rust-screen-theme-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("theme.toml");
+    fs::write(&path, "[title]\nheight = 12\n").unwrap();
+    let mut watcher = ThemeWatcher::new(path.clone());
+    thread::sleep(Duration::from_millis(1100));
+    assert!(watcher.poll().is_none());
+
+    fs::write(&path, "[title]\nheight = 20\n").unwrap();
+    thread::sleep(Duration::from_millis(1100));
+    let theme = watcher.poll().unwrap().unwrap();
+    assert_eq!(theme.title_height, 20);
+
+    //a broken edit is reported, not applied
+    fs::write(&path, "[title]\nheight = \"tall\"\n").unwrap();
+    thread::sleep(Duration::from_millis(1100));
+    assert!(watcher.poll().unwrap().is_err());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn magnified_windows_are_bigger_on_screen() {
+    let theme = Theme::default();
+    let mut win = window();
+    win.scale = 3;
+    let b = win.bounds();
+    assert_eq!((b.x, b.y, b.width, b.height), (50, 60, 300, 240));
+    assert!(win.contains(&Point { x: 340, y: 290 }));
+    let local = win.to_local(&Point { x: 340, y: 290 });
+    assert_eq!((local.x, local.y), (96, 76));
+    //the frame goes around the magnified window
+    assert_eq!(theme.title_bar(&win).width, 302);
+}
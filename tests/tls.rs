@@ -0,0 +1,141 @@
+use std::fs::{read, write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use futures_util::StreamExt;
+use native_tls::{Certificate, Identity};
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+use tokio_tungstenite::accept_hdr_async;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response, ErrorResponse};
+use rust_screen::connection::connect;
+use rust_screen::tls::{ConnectOptions, make_request, load_pins, check_pin};
+
+//self signed certs for localhost and 127.0.0.1. cert.pem has a key, other.pem is only a cert
+//that doesn't match it
+const CERT:&str = "test/tls/cert.pem";
+const KEY:&str = "test/tls/cert-key.pem";
+const OTHER:&str = "test/tls/other.pem";
+
+fn opts(ca_cert:Option<&str>, pin_cert:Option<&str>, token:Option<&str>) -> ConnectOptions {
+    ConnectOptions {
+        ca_cert: ca_cert.map(PathBuf::from),
+        pin_cert: pin_cert.map(PathBuf::from),
+        token: token.map(|t|t.to_string()),
+    }
+}
+
+fn temp_file(name:&str, contents:&[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rust-screen-{}-{}", std::process::id(), name));
+    write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn the_token_goes_in_a_bearer_header() {
+    let request = make_request("ws://localhost:8080", &opts(None, None, Some("secret"))).unwrap();
+    assert_eq!(request.headers()["Authorization"], "Bearer secret");
+
+    let request = make_request("ws://localhost:8080", &opts(None, None, None)).unwrap();
+    assert!(request.headers().get("Authorization").is_none());
+}
+
+#[test]
+fn bad_tokens_and_urls_are_refused() {
+    let err = make_request("ws://localhost:8080", &opts(None, None, Some("sec\nret"))).unwrap_err();
+    assert!(err.contains("header"), "{}", err);
+    assert!(make_request("not a url", &opts(None, None, None)).is_err());
+}
+
+#[test]
+fn pins_load_from_bundles() {
+    let mut bundle = read(CERT).unwrap();
+    bundle.extend(read(OTHER).unwrap());
+    let path = temp_file("bundle.pem", &bundle);
+    let pins = load_pins(&path).unwrap();
+    assert_eq!(pins.len(), 2);
+    assert_ne!(pins[0], pins[1]);
+
+    let empty = temp_file("empty.pem", b"");
+    assert!(load_pins(&empty).unwrap_err().contains("no PEM certificates"));
+
+    let garbage = temp_file("garbage.pem", b"-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n");
+    assert!(load_pins(&garbage).unwrap_err().contains("bad certificate"));
+
+    assert!(load_pins(&PathBuf::from("test/tls/missing.pem")).unwrap_err().contains("could not read"));
+}
+
+#[test]
+fn only_the_pinned_certificate_matches() {
+    let pins = load_pins(&PathBuf::from(CERT)).unwrap();
+    let cert = Certificate::from_pem(&read(CERT).unwrap()).unwrap();
+    let other = Certificate::from_pem(&read(OTHER).unwrap()).unwrap();
+    assert!(check_pin("server", &pins, Some(&cert)).is_ok());
+    assert!(check_pin("server", &pins, Some(&other)).unwrap_err().contains("doesn't match"));
+    assert!(check_pin("server", &pins, None).unwrap_err().contains("no certificate"));
+}
+
+//a wss server using cert.pem. returns its url and the Authorization header of every
+//websocket handshake that reached it
+async fn tls_server() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let identity = Identity::from_pkcs8(&read(CERT).unwrap(), &read(KEY).unwrap()).unwrap();
+    let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("wss://localhost:{}", listener.local_addr().unwrap().port());
+    let handshakes = Arc::new(Mutex::new(vec![]));
+    let seen = handshakes.clone();
+    tokio::spawn(async move {
+        loop {
+            let (tcp, _) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(_) => return,
+            };
+            let acceptor = acceptor.clone();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                //clients that reject the certificate drop the connection here
+                let tls = match acceptor.accept(tcp).await {
+                    Ok(tls) => tls,
+                    Err(_) => return,
+                };
+                let record = |req:&Request, res:Response| -> Result<Response, ErrorResponse> {
+                    let auth = req.headers().get("Authorization").map(|v|v.to_str().unwrap().to_string());
+                    seen.lock().unwrap().push(auth);
+                    Ok(res)
+                };
+                if let Ok(mut ws) = accept_hdr_async(tls, record).await {
+                    while let Some(Ok(_)) = ws.next().await {}
+                }
+            });
+        }
+    });
+    (url, handshakes)
+}
+
+#[tokio::test]
+async fn a_pinned_server_gets_the_token() {
+    let (url, handshakes) = tls_server().await;
+    let ws = connect(&url, &opts(None, Some(CERT), Some("secret"))).await;
+    assert!(ws.is_ok(), "{:?}", ws.err());
+    assert_eq!(*handshakes.lock().unwrap(), vec![Some("Bearer secret".to_string())]);
+}
+
+#[tokio::test]
+async fn a_server_with_another_certificate_gets_nothing() {
+    let (url, handshakes) = tls_server().await;
+    let err = connect(&url, &opts(None, Some(OTHER), Some("secret"))).await.err().unwrap();
+    assert!(err.contains("doesn't match --pin-cert"), "{}", err);
+    assert!(handshakes.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn self_signed_servers_need_a_ca_or_a_pin() {
+    let (url, handshakes) = tls_server().await;
+    let err = connect(&url, &opts(None, None, None)).await.err().unwrap();
+    assert!(err.contains("TLS error"), "{}", err);
+    assert!(handshakes.lock().unwrap().is_empty());
+
+    //the self signed cert is its own CA
+    let ws = connect(&url, &opts(Some(CERT), None, None)).await;
+    assert!(ws.is_ok(), "{:?}", ws.err());
+    assert_eq!(*handshakes.lock().unwrap(), vec![None]);
+}
@@ -0,0 +1,29 @@
+//keeping windows on the screen when it changes size
+mod common;
+
+use rust_screen::window::Bounds;
+use common::{SCREEN_BOUNDS, xy};
+
+#[test]
+fn windows_inside_stay_put() {
+    let win = Bounds { x: 100, y: 100, width: 200, height: 100 };
+    assert_eq!(xy(win.keep_inside(&SCREEN_BOUNDS)), (100, 100));
+}
+
+#[test]
+fn windows_off_an_edge_are_pulled_back() {
+    let right = Bounds { x: 600, y: 100, width: 200, height: 100 };
+    assert_eq!(xy(right.keep_inside(&SCREEN_BOUNDS)), (440, 100));
+    let bottom = Bounds { x: 100, y: 450, width: 200, height: 100 };
+    assert_eq!(xy(bottom.keep_inside(&SCREEN_BOUNDS)), (100, 380));
+    let top_left = Bounds { x: -50, y: -10, width: 200, height: 100 };
+    assert_eq!(xy(top_left.keep_inside(&SCREEN_BOUNDS)), (0, 0));
+}
+
+#[test]
+fn windows_bigger_than_the_screen_keep_their_corner_on_it() {
+    let huge = Bounds { x: 300, y: 200, width: 1000, height: 800 };
+    let inside = huge.keep_inside(&SCREEN_BOUNDS);
+    assert_eq!(xy(inside), (0, 0));
+    assert_eq!((inside.width, inside.height), (1000, 800));
+}